@@ -0,0 +1,98 @@
+//! Milestones of the farm, kept with the time they were reached.
+
+use std::{fmt, str::FromStr};
+
+use crate::resources::{Resource, Resource::*};
+
+#[derive(Clone, Debug)]
+pub struct Achievement {
+    /// Identifies the achievement in the save.
+    pub name: &'static str,
+    pub label: &'static str,
+    /// Reached once there's at least this much of the resource.
+    pub goal: (Resource, u64),
+}
+
+pub fn achievements() -> Vec<Achievement> {
+    vec![
+        Achievement {
+            name: "first_chick",
+            label: "First chick",
+            goal: (Chicks, 1),
+        },
+        Achievement {
+            name: "hundred_nests",
+            label: "A hundred nests",
+            goal: (Nests, 100),
+        },
+        Achievement {
+            name: "runaways",
+            label: "1000 chicks ran away",
+            goal: (Runaway, 1_000),
+        },
+        Achievement {
+            name: "big_flock",
+            label: "10000 chickens",
+            goal: (Chickens, 10_000),
+        },
+        Achievement {
+            name: "explorers",
+            label: "Ten explorers",
+            goal: (Explorers, 10),
+        },
+        Achievement {
+            name: "million_corn",
+            label: "A million corn",
+            goal: (Corn, 1_000_000),
+        },
+    ]
+}
+
+/// The names of the achievements reached so far and the unix time they were reached,
+/// saved under a single key.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Unlocked(pub Vec<(String, f64)>);
+
+impl Unlocked {
+    /// When the achievement called `name` was reached, if it was.
+    pub fn time(&self, name: &str) -> Option<f64> {
+        self.0
+            .iter()
+            .find(|(unlocked, _)| unlocked == name)
+            .map(|&(_, time)| time)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.time(name).is_some()
+    }
+}
+
+impl fmt::Display for Unlocked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (name, time)) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ";")?;
+            }
+            write!(f, "{}@{}", name, time)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Unlocked {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(';')
+            .filter(|s| !s.is_empty())
+            .map(|entry| {
+                let (name, time) = entry
+                    .split_once('@')
+                    .ok_or_else(|| format!("invalid achievement `{}`", entry))?;
+                let time = time.parse().map_err(|e| format!("{}: {}", entry, e))?;
+                Ok((name.to_owned(), time))
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
@@ -0,0 +1,279 @@
+use std::fmt;
+
+use crate::save;
+
+/// The balance the game ships with.
+const DEFAULT: &str = include_str!("../balance.toml");
+
+macro_rules! balance {
+    ($($name:ident,)*) => {
+        /// Numbers the game rules are made of, see `balance.toml` for what they mean.
+        #[derive(Copy, Clone, Debug)]
+        pub struct Balance {
+            $(pub $name: u64,)*
+        }
+
+        impl Balance {
+            const KEYS: &'static [&'static str] = &[$(stringify!($name)),*];
+
+            fn zero() -> Self {
+                Self { $($name: 0,)* }
+            }
+
+            fn field(&mut self, key: &str) -> Option<&mut u64> {
+                match key {
+                    $(stringify!($name) => Some(&mut self.$name),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+balance! {
+    breeding_per_batch,
+    chicks_per_nest,
+    growing_up_ticks,
+    runaway_factor,
+    runaway_divisor,
+    round,
+    corn_per_fetch,
+    fetchers_per_trip,
+    travel_per_step,
+    forage_distance,
+    eggs_per_nest,
+    egg_cap,
+    explore_work,
+    scout_cost,
+    event_chance,
+    event_ticks,
+    fox_percent,
+    fox_roosters,
+    storm_percent,
+    shore_up_corn,
+    flock_percent,
+    welcome_corn,
+    feathers_divisor,
+    feather_percent,
+    coop_cost,
+    field_cost,
+    eggs_per_coop_nest,
+    corn_per_field,
+}
+
+#[derive(Debug)]
+pub struct BalanceError {
+    /// Line in the file, if the problem is with a specific line.
+    line: Option<usize>,
+    msg: String,
+}
+
+impl fmt::Display for BalanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.msg),
+            None => write!(f, "{}", self.msg),
+        }
+    }
+}
+
+impl Balance {
+    /// The default balance, with `balance.toml` from the save directory applied on top
+    /// (on native platforms).
+    pub fn load() -> Result<Self, BalanceError> {
+        match save::read_file("balance.toml") {
+            Some(src) => Self::default().with_overrides(&src),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// This balance with the values set in `src` replaced, see `apply`.
+    fn with_overrides(mut self, src: &str) -> Result<Self, BalanceError> {
+        self.apply(src)?;
+        self.validate()?;
+        Ok(self)
+    }
+
+    /// Parse a complete balance file.
+    pub fn parse(src: &str) -> Result<Self, BalanceError> {
+        let mut this = Self::zero();
+        let seen = this.apply(src)?;
+        if let Some(key) = Self::KEYS.iter().find(|&&key| !seen.contains(&key)) {
+            return Err(BalanceError {
+                line: None,
+                msg: format!("missing `{}`", key),
+            });
+        }
+        this.validate()?;
+        Ok(this)
+    }
+
+    /// Override the values set in `src`, which is a flat subset of TOML: `key = value`
+    /// lines with integer values and `#` comments. Returns the keys that were set.
+    fn apply<'a>(&mut self, src: &'a str) -> Result<Vec<&'a str>, BalanceError> {
+        let mut seen = vec![];
+        for (i, line) in src.lines().enumerate() {
+            let err = |msg| BalanceError {
+                line: Some(i + 1),
+                msg,
+            };
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| err(format!("expected `key = value`, found `{}`", line)))?;
+            let key = key.trim();
+            if seen.contains(&key) {
+                return Err(err(format!("`{}` is set twice", key)));
+            }
+            let field = self
+                .field(key)
+                .ok_or_else(|| err(format!("unknown key `{}`", key)))?;
+            *field = value
+                .trim()
+                .replace('_', "")
+                .parse()
+                .map_err(|e| err(format!("`{}`: {}", key, e)))?;
+            seen.push(key);
+        }
+        Ok(seen)
+    }
+
+    fn validate(&self) -> Result<(), BalanceError> {
+        let err = |msg: &str| {
+            Err(BalanceError {
+                line: None,
+                msg: msg.to_owned(),
+            })
+        };
+        let nonzero = [
+            ("breeding_per_batch", self.breeding_per_batch),
+            ("chicks_per_nest", self.chicks_per_nest),
+            ("growing_up_ticks", self.growing_up_ticks),
+            ("runaway_divisor", self.runaway_divisor),
+            ("round", self.round),
+            ("fetchers_per_trip", self.fetchers_per_trip),
+            ("explore_work", self.explore_work),
+            ("event_chance", self.event_chance),
+            ("event_ticks", self.event_ticks),
+            ("fox_percent", self.fox_percent),
+            ("storm_percent", self.storm_percent),
+            ("flock_percent", self.flock_percent),
+            ("feathers_divisor", self.feathers_divisor),
+        ];
+        for (key, val) in nonzero {
+            if val == 0 {
+                return err(&format!("`{}` must not be 0", key));
+            }
+        }
+        if self.runaway_factor < self.runaway_divisor {
+            return err("`runaway_factor` must be at least `runaway_divisor`");
+        }
+        Ok(())
+    }
+
+    /// Scale the numbers named in `modifiers` to the given percentage of their value.
+    /// Numbers that weren't 0 stay at least 1.
+    pub fn modified(mut self, modifiers: &[(&str, u64)]) -> Self {
+        for &(key, percent) in modifiers {
+            let field = self
+                .field(key)
+                .unwrap_or_else(|| panic!("unknown balance key `{}`", key));
+            if *field > 0 {
+                *field = (*field * percent / 100).max(1);
+            }
+        }
+        self
+    }
+
+    /// How many of the chicks hatched from `n` nests run away in step `i` of growing up.
+    pub fn chicks_running_away(&self, n: u64, i: u64) -> u64 {
+        let runaway = self.runaway_factor * n / self.runaway_divisor;
+        if runaway > self.growing_up_ticks {
+            runaway / self.growing_up_ticks
+        } else if i % runaway == 0 {
+            1
+        } else {
+            0
+        }
+    }
+}
+
+impl Default for Balance {
+    fn default() -> Self {
+        Self::parse(DEFAULT).unwrap_or_else(|err| panic!("built-in balance.toml: {}", err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(result: Result<Balance, BalanceError>) -> String {
+        result.unwrap_err().to_string()
+    }
+
+    #[test]
+    fn overrides() {
+        let default = Balance::default();
+        let balance = default
+            .with_overrides("# faster\nround = 1_200 # twice\n\nchicks_per_nest=3\n")
+            .unwrap();
+        assert_eq!(balance.round, 1_200);
+        assert_eq!(balance.chicks_per_nest, 3);
+        let mut expected = default;
+        expected.round = 1_200;
+        expected.chicks_per_nest = 3;
+        assert_eq!(format!("{:?}", balance), format!("{:?}", expected));
+        assert_eq!(
+            format!("{:?}", default.with_overrides("").unwrap()),
+            format!("{:?}", default)
+        );
+    }
+
+    #[test]
+    fn invalid_overrides() {
+        let default = Balance::default();
+        let cases = [
+            ("round = 1\nrounds = 2", "line 2: unknown key `rounds`"),
+            ("round = 1\n\nround = 2", "line 3: `round` is set twice"),
+            (
+                "round = 1.5",
+                "line 1: `round`: invalid digit found in string",
+            ),
+            (
+                "round = -1",
+                "line 1: `round`: invalid digit found in string",
+            ),
+            ("round", "line 1: expected `key = value`, found `round`"),
+            ("feathers_divisor = 0", "`feathers_divisor` must not be 0"),
+            ("runaway_divisor = 0", "`runaway_divisor` must not be 0"),
+            (
+                "runaway_factor = 6\nrunaway_divisor = 7",
+                "`runaway_factor` must be at least `runaway_divisor`",
+            ),
+        ];
+        for (src, msg) in cases {
+            assert_eq!(error(default.with_overrides(src)), msg, "{:?}", src);
+        }
+    }
+
+    #[test]
+    fn complete_files() {
+        assert!(Balance::parse(DEFAULT).is_ok());
+        let missing: String = DEFAULT
+            .lines()
+            .filter(|line| !line.starts_with("scout_cost"))
+            .map(|line| format!("{}\n", line))
+            .collect();
+        assert_eq!(error(Balance::parse(&missing)), "missing `scout_cost`");
+        let unknown = format!("{}\nsunshine = 1", DEFAULT.trim_end());
+        let lines = DEFAULT.trim_end().lines().count() + 1;
+        assert_eq!(
+            error(Balance::parse(&unknown)),
+            format!("line {}: unknown key `sunshine`", lines)
+        );
+    }
+}
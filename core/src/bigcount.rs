@@ -0,0 +1,361 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    ops::{AddAssign, MulAssign, SubAssign},
+    str::FromStr,
+};
+
+/// Every limb holds nine decimal digits, so printing and parsing are trivial.
+const BASE: u64 = 1_000_000_000;
+const DIGITS: usize = 9;
+
+/// A resource count without an upper bound. Subtraction saturates at zero instead of
+/// panicking. Saved as its decimal digits, so a `Saveable<u64>` can be turned into a
+/// `Saveable<BigCount>` without losing the saved value.
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct BigCount {
+    /// Base `BASE` digits, least significant first, without leading zeros.
+    limbs: Vec<u32>,
+}
+
+impl BigCount {
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    fn trim(&mut self) {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+    }
+
+    /// `None` if it doesn't fit.
+    pub fn to_u64(&self) -> Option<u64> {
+        self.limbs.iter().rev().try_fold(0_u64, |acc, &limb| {
+            acc.checked_mul(BASE)?.checked_add(limb as u64)
+        })
+    }
+
+    pub fn saturating_to_u64(&self) -> u64 {
+        self.to_u64().unwrap_or(u64::MAX)
+    }
+
+    /// Roughly the value, for rates and display.
+    pub fn to_f64(&self) -> f64 {
+        self.limbs
+            .iter()
+            .rev()
+            .fold(0.0, |acc, &limb| acc * BASE as f64 + limb as f64)
+    }
+
+    /// `None` if `other` is larger.
+    pub fn checked_sub(&self, other: &Self) -> Option<Self> {
+        if *self < *other {
+            return None;
+        }
+        let mut result = self.clone();
+        let mut borrow = 0;
+        for i in 0..result.limbs.len() {
+            let sub = other.limbs.get(i).copied().unwrap_or(0) as u64 + borrow;
+            let limb = result.limbs[i] as u64;
+            if limb >= sub {
+                result.limbs[i] = (limb - sub) as u32;
+                borrow = 0;
+            } else {
+                result.limbs[i] = (limb + BASE - sub) as u32;
+                borrow = 1;
+            }
+        }
+        result.trim();
+        Some(result)
+    }
+
+    /// Zero if `other` is larger.
+    pub fn saturating_sub(&self, other: &Self) -> Self {
+        self.checked_sub(other).unwrap_or_default()
+    }
+
+    fn mul_small(&mut self, n: u64) {
+        let mut carry: u128 = 0;
+        for limb in &mut self.limbs {
+            let val = *limb as u128 * n as u128 + carry;
+            *limb = (val % BASE as u128) as u32;
+            carry = val / BASE as u128;
+        }
+        while carry > 0 {
+            self.limbs.push((carry % BASE as u128) as u32);
+            carry /= BASE as u128;
+        }
+        self.trim();
+    }
+}
+
+impl From<u64> for BigCount {
+    fn from(mut n: u64) -> Self {
+        let mut limbs = vec![];
+        while n > 0 {
+            limbs.push((n % BASE) as u32);
+            n /= BASE;
+        }
+        Self { limbs }
+    }
+}
+
+impl Ord for BigCount {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.limbs
+            .len()
+            .cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigCount {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq<u64> for BigCount {
+    fn eq(&self, other: &u64) -> bool {
+        self.to_u64() == Some(*other)
+    }
+}
+
+impl PartialOrd<u64> for BigCount {
+    fn partial_cmp(&self, other: &u64) -> Option<Ordering> {
+        Some(match self.to_u64() {
+            Some(n) => n.cmp(other),
+            None => Ordering::Greater,
+        })
+    }
+}
+
+impl AddAssign<&BigCount> for BigCount {
+    fn add_assign(&mut self, rhs: &BigCount) {
+        if self.limbs.len() < rhs.limbs.len() {
+            self.limbs.resize(rhs.limbs.len(), 0);
+        }
+        let mut carry = 0;
+        for i in 0..self.limbs.len() {
+            let sum = self.limbs[i] as u64 + rhs.limbs.get(i).copied().unwrap_or(0) as u64 + carry;
+            self.limbs[i] = (sum % BASE) as u32;
+            carry = sum / BASE;
+        }
+        if carry > 0 {
+            self.limbs.push(carry as u32);
+        }
+    }
+}
+
+impl AddAssign for BigCount {
+    fn add_assign(&mut self, rhs: BigCount) {
+        *self += &rhs;
+    }
+}
+
+impl AddAssign<u64> for BigCount {
+    fn add_assign(&mut self, rhs: u64) {
+        *self += &BigCount::from(rhs);
+    }
+}
+
+/// Saturates at zero.
+impl SubAssign<&BigCount> for BigCount {
+    fn sub_assign(&mut self, rhs: &BigCount) {
+        *self = self.saturating_sub(rhs);
+    }
+}
+
+/// Saturates at zero.
+impl SubAssign for BigCount {
+    fn sub_assign(&mut self, rhs: BigCount) {
+        *self -= &rhs;
+    }
+}
+
+/// Saturates at zero.
+impl SubAssign<u64> for BigCount {
+    fn sub_assign(&mut self, rhs: u64) {
+        *self -= &BigCount::from(rhs);
+    }
+}
+
+impl MulAssign<&BigCount> for BigCount {
+    fn mul_assign(&mut self, rhs: &BigCount) {
+        let mut limbs = vec![0_u64; self.limbs.len() + rhs.limbs.len()];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0;
+            for (j, &b) in rhs.limbs.iter().enumerate() {
+                let val = limbs[i + j] + a as u64 * b as u64 + carry;
+                limbs[i + j] = val % BASE;
+                carry = val / BASE;
+            }
+            limbs[i + rhs.limbs.len()] += carry;
+        }
+        self.limbs = limbs.into_iter().map(|limb| limb as u32).collect();
+        self.trim();
+    }
+}
+
+impl MulAssign for BigCount {
+    fn mul_assign(&mut self, rhs: BigCount) {
+        *self *= &rhs;
+    }
+}
+
+impl MulAssign<u64> for BigCount {
+    fn mul_assign(&mut self, rhs: u64) {
+        self.mul_small(rhs);
+    }
+}
+
+impl fmt::Display for BigCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut limbs = self.limbs.iter().rev();
+        match limbs.next() {
+            Some(top) => write!(f, "{}", top)?,
+            None => return write!(f, "0"),
+        }
+        for limb in limbs {
+            write!(f, "{:0width$}", limb, width = DIGITS)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for BigCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl FromStr for BigCount {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(format!("`{}` is not a count", s));
+        }
+        let mut limbs = vec![];
+        let mut end = s.len();
+        while end > 0 {
+            let start = end.saturating_sub(DIGITS);
+            limbs.push(s[start..end].parse().unwrap());
+            end = start;
+        }
+        let mut this = Self { limbs };
+        this.trim();
+        Ok(this)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::save::{self, Saveable};
+
+    fn big(s: &str) -> BigCount {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn carry_across_limbs() {
+        let mut n = BigCount::from(999_999_999);
+        n += 1;
+        assert_eq!(n.to_string(), "1000000000");
+        let mut n = big("999999999999999999999999999");
+        n += &BigCount::from(1);
+        assert_eq!(n.to_string(), "1000000000000000000000000000");
+    }
+
+    #[test]
+    fn borrow_across_limbs() {
+        let mut n = big("1000000000000000000");
+        n -= 1;
+        assert_eq!(n.to_string(), "999999999999999999");
+        assert_eq!(
+            big("1000000000000000000").checked_sub(&big("999999999999999999")),
+            Some(BigCount::from(1))
+        );
+    }
+
+    #[test]
+    fn subtraction_saturates() {
+        let mut n = BigCount::from(5);
+        n -= 7;
+        assert!(n.is_zero());
+        assert_eq!(BigCount::from(5).checked_sub(&BigCount::from(7)), None);
+        assert_eq!(big("1000000000").checked_sub(&big("1000000001")), None);
+    }
+
+    #[test]
+    fn multiplication_matches_u128() {
+        let cases: [(u128, u128); 4] = [
+            (0, 12345),
+            (999_999_999, 999_999_999),
+            (123_456_789_012_345, 987_654_321),
+            (u64::MAX as u128, u64::MAX as u128),
+        ];
+        for (a, b) in cases {
+            let mut n = big(&a.to_string());
+            n *= &big(&b.to_string());
+            assert_eq!(n.to_string(), (a * b).to_string());
+            let mut n = big(&a.to_string());
+            n *= b as u64;
+            assert_eq!(n.to_string(), (a * b).to_string());
+        }
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for s in [
+            "0",
+            "1",
+            "999999999",
+            "1000000000",
+            "1000000001",
+            "123000000004",
+            "18446744073709551616",
+            "100000000000000000000000000000",
+        ] {
+            assert_eq!(big(s).to_string(), s);
+        }
+        assert_eq!(big("000000000000042").to_string(), "42");
+        assert_eq!(big("000").to_string(), "0");
+        assert!("".parse::<BigCount>().is_err());
+        assert!("-1".parse::<BigCount>().is_err());
+        assert!("1e9".parse::<BigCount>().is_err());
+    }
+
+    #[test]
+    fn order() {
+        assert!(big("1000000000") > big("999999999"));
+        assert!(big("2000000000") > big("1999999999"));
+        assert!(big("1000000001") < big("2000000000"));
+        assert_eq!(big("0001").cmp(&BigCount::from(1)), Ordering::Equal);
+        assert!(big("18446744073709551616") > u64::MAX);
+        let seven = BigCount::from(7);
+        assert!(seven < 8 && seven > 6 && seven == 7);
+        assert_eq!(BigCount::from(u64::MAX).to_u64(), Some(u64::MAX));
+        assert_eq!(big("18446744073709551616").to_u64(), None);
+    }
+
+    #[test]
+    fn saveable() {
+        let _root = save::init_temp("bigcount-saveable");
+        let mut old: Saveable<u64> = Saveable::new(0_u64, "corn");
+        save::transaction(|| old.set(u64::MAX));
+
+        // Saved as a `u64` before.
+        let mut corn: Saveable<BigCount> = Saveable::default("corn");
+        assert_eq!(*corn, BigCount::from(u64::MAX));
+        save::transaction(|| corn.update(|corn| *corn *= 1_000));
+        let corn: Saveable<BigCount> = Saveable::default("corn");
+        assert_eq!(corn.to_string(), "18446744073709551615000");
+    }
+}
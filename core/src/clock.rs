@@ -0,0 +1,30 @@
+/// Simulation steps per second, independent of the frame rate.
+pub const TICKS_PER_SECOND: u64 = 60;
+const STEP: f64 = 1.0 / TICKS_PER_SECOND as f64;
+/// If a frame took longer than this (e.g. a throttled background tab), the rest of the
+/// time is dropped instead of trying to catch up in a single frame.
+const MAX_CATCH_UP: u64 = 10 * TICKS_PER_SECOND;
+
+/// Turns real time into a number of fixed simulation steps.
+pub struct Clock {
+    accumulated: f64,
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Self { accumulated: 0.0 }
+    }
+
+    /// Let `dt` seconds pass and return how many steps to simulate.
+    pub fn advance(&mut self, dt: f64) -> u64 {
+        self.accumulated += dt.max(0.0);
+        let mut steps = (self.accumulated / STEP) as u64;
+        if steps > MAX_CATCH_UP {
+            steps = MAX_CATCH_UP;
+            self.accumulated = 0.0;
+        } else {
+            self.accumulated -= steps as f64 * STEP;
+        }
+        steps
+    }
+}
@@ -0,0 +1,428 @@
+use std::{
+    cell::{Cell, RefCell},
+    cmp::Ordering,
+    fmt::Display,
+    ops::*,
+    rc::{Rc, Weak},
+};
+
+use crate::save::Save;
+
+mod history;
+mod sparse;
+mod sync;
+pub use history::{History, Sample};
+pub use sparse::SparseMap;
+pub use sync::{SyncReader, SyncSensor};
+
+pub struct Sensor<T> {
+    input: Rc<Shared<T>>,
+}
+
+struct Shared<T> {
+    value: RefCell<T>,
+    /// Bumped on every write, so readers can find out whether they missed one.
+    version: Cell<u64>,
+    subscribers: RefCell<Vec<Subscriber<T>>>,
+    /// Whether `notify` is running, so `subscribers` is taken.
+    notifying: Cell<bool>,
+    /// Unsubscribed during the current notification.
+    removed: RefCell<Vec<SubscriptionId>>,
+    next_id: Cell<u64>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct SubscriptionId(u64);
+
+type Subscriber<T> = (SubscriptionId, Box<dyn FnMut(&T)>);
+
+impl<T> Shared<T> {
+    fn subscribe(&self, f: impl FnMut(&T) + 'static) -> SubscriptionId {
+        let id = SubscriptionId(self.next_id.get());
+        self.next_id.set(id.0 + 1);
+        self.subscribers.borrow_mut().push((id, Box::new(f)));
+        id
+    }
+    fn unsubscribe(&self, id: SubscriptionId) {
+        self.subscribers.borrow_mut().retain(|(i, _)| *i != id);
+        if self.notifying.get() {
+            self.removed.borrow_mut().push(id);
+        }
+    }
+    fn notify(&self) {
+        // Subscribers may (un)subscribe, so don't hold the borrow. They may not write
+        // to the sensor they are notified about though, as the value is borrowed.
+        let mut subscribers = std::mem::take(&mut *self.subscribers.borrow_mut());
+        self.notifying.set(true);
+        {
+            let val = self.value.borrow();
+            for (id, f) in &mut subscribers {
+                if !self.removed.borrow().contains(id) {
+                    f(&val);
+                }
+            }
+        }
+        self.notifying.set(false);
+        let removed = std::mem::take(&mut *self.removed.borrow_mut());
+        subscribers.retain(|(id, _)| !removed.contains(id));
+        let mut current = self.subscribers.borrow_mut();
+        subscribers.append(&mut current);
+        *current = subscribers;
+    }
+}
+
+impl<T> Sensor<T> {
+    /// Just create a sensor, leaving reader creation to later.
+    pub fn raw(val: T) -> Self {
+        let input = Rc::new(Shared {
+            value: RefCell::new(val),
+            version: Cell::new(0),
+            subscribers: Default::default(),
+            notifying: Cell::new(false),
+            removed: Default::default(),
+            next_id: Cell::new(0),
+        });
+        Self { input }
+    }
+    /// Create a sensor and a reader at the same time
+    pub fn new(val: T) -> (Self, Reader<T>) {
+        let this = Self::raw(val);
+        let reader = this.make_reader();
+        (this, reader)
+    }
+    pub fn make_reader(&self) -> Reader<T> {
+        let output = Rc::downgrade(&self.input);
+        Reader {
+            output,
+            seen: Cell::new(self.version()),
+        }
+    }
+    pub fn set(&self, val: T) {
+        self.update(|v| *v = val)
+    }
+    pub fn update(&self, f: impl FnOnce(&mut T)) {
+        f(&mut self.input.value.borrow_mut());
+        self.input.version.set(self.version() + 1);
+        self.input.notify();
+    }
+    pub fn modify(&self, f: impl FnOnce(T) -> T) -> T
+    where
+        T: Clone,
+    {
+        let new = f(self.get());
+        self.set(new.clone());
+        new
+    }
+    pub fn get(&self) -> T
+    where
+        T: Clone,
+    {
+        self.with(T::clone)
+    }
+    /// Look at the value without copying it.
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        f(&self.input.value.borrow())
+    }
+    /// Number of writes so far.
+    pub fn version(&self) -> u64 {
+        self.input.version.get()
+    }
+    /// Call `f` with the new value after every write, removed again by `unsubscribe`.
+    pub fn subscribe(&self, f: impl FnMut(&T) + 'static) -> SubscriptionId {
+        self.input.subscribe(f)
+    }
+    pub fn unsubscribe(&self, id: SubscriptionId) {
+        self.input.unsubscribe(id)
+    }
+}
+
+pub struct Reader<T> {
+    output: Weak<Shared<T>>,
+    /// Sensor version at the last `changed` call.
+    seen: Cell<u64>,
+}
+
+impl<T> Reader<T> {
+    /// Whether the sensor was written to since the last call (or since the reader was
+    /// created). Always `false` once the sensor is gone.
+    pub fn changed(&self) -> bool {
+        match self.output.upgrade() {
+            Some(o) => self.seen.replace(o.version.get()) != o.version.get(),
+            None => false,
+        }
+    }
+    /// Like `Sensor::subscribe`, `None` if the sensor is gone.
+    pub fn subscribe(&self, f: impl FnMut(&T) + 'static) -> Option<SubscriptionId> {
+        self.output.upgrade().map(|o| o.subscribe(f))
+    }
+    pub fn unsubscribe(&self, id: SubscriptionId) {
+        if let Some(o) = self.output.upgrade() {
+            o.unsubscribe(id)
+        }
+    }
+}
+
+impl<T> Clone for Reader<T> {
+    fn clone(&self) -> Self {
+        Self {
+            output: self.output.clone(),
+            seen: self.seen.clone(),
+        }
+    }
+}
+
+/// Something that can be read while its sensor is alive, like a `Reader` or a
+/// projection of one.
+pub trait Source {
+    type Value;
+    /// `None` if the sensor is gone.
+    fn with<R>(&self, f: impl FnOnce(&Self::Value) -> R) -> Option<R>;
+    fn get(&self) -> Option<Self::Value>
+    where
+        Self::Value: Clone,
+    {
+        self.with(Self::Value::clone)
+    }
+    /// Only look at a part of (or something computed from) the value.
+    fn map<U, F: Fn(&Self::Value) -> U>(self, f: F) -> Map<Self, F>
+    where
+        Self: Sized,
+    {
+        Map { source: self, f }
+    }
+    /// Read two sources at once.
+    fn zip<O: Source>(self, other: O) -> Zip<Self, O>
+    where
+        Self: Sized,
+    {
+        Zip(self, other)
+    }
+}
+
+impl<T> Source for Reader<T> {
+    type Value = T;
+    fn with<R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        self.output.upgrade().map(|o| f(&o.value.borrow()))
+    }
+}
+
+pub struct Map<S, F> {
+    source: S,
+    f: F,
+}
+
+impl<S: Source, U, F: Fn(&S::Value) -> U> Source for Map<S, F> {
+    type Value = U;
+    fn with<R>(&self, f: impl FnOnce(&U) -> R) -> Option<R> {
+        self.source.with(|val| f(&(self.f)(val)))
+    }
+}
+
+pub struct Zip<A, B>(A, B);
+
+impl<A: Source, B: Source> Source for Zip<A, B>
+where
+    A::Value: Clone,
+    B::Value: Clone,
+{
+    type Value = (A::Value, B::Value);
+    fn with<R>(&self, f: impl FnOnce(&Self::Value) -> R) -> Option<R> {
+        let a = self.0.get()?;
+        let b = self.1.get()?;
+        Some(f(&(a, b)))
+    }
+}
+
+/// One or more readers whose current values can be compared to find out whether
+/// anything derived from them needs to be recomputed.
+pub trait Inputs {
+    type Values: PartialEq;
+    /// `None` if any of the sensors is gone.
+    fn read(&self) -> Option<Self::Values>;
+}
+
+impl<S: Source> Inputs for S
+where
+    S::Value: Clone + PartialEq,
+{
+    type Values = S::Value;
+    fn read(&self) -> Option<S::Value> {
+        self.get()
+    }
+}
+
+macro_rules! tuple_inputs {
+    ($($name:ident),*) => {
+        impl<$($name: Inputs),*> Inputs for ($($name,)*) {
+            type Values = ($($name::Values,)*);
+            #[allow(non_snake_case)]
+            fn read(&self) -> Option<Self::Values> {
+                let ($($name,)*) = self;
+                Some(($($name.read()?,)*))
+            }
+        }
+    };
+}
+
+tuple_inputs!(A);
+tuple_inputs!(A, B);
+tuple_inputs!(A, B, C);
+tuple_inputs!(A, B, C, D);
+
+/// A value derived from one or more readers. It is only recomputed when it is
+/// looked at and its inputs changed since the last computation.
+pub struct Computed<I: Inputs, T> {
+    inputs: I,
+    f: Box<dyn Fn(I::Values) -> T>,
+    cache: RefCell<Option<(I::Values, T)>>,
+}
+
+impl<I: Inputs, T> Computed<I, T>
+where
+    I::Values: Clone,
+{
+    pub fn new(inputs: I, f: impl Fn(I::Values) -> T + 'static) -> Self {
+        Self {
+            inputs,
+            f: Box::new(f),
+            cache: RefCell::new(None),
+        }
+    }
+    /// Recompute the value if the inputs changed. Returns whether that happened.
+    pub fn refresh(&self) -> bool {
+        self.recompute().unwrap_or(false)
+    }
+    fn recompute(&self) -> Option<bool> {
+        let values = self.inputs.read()?;
+        let mut cache = self.cache.borrow_mut();
+        if matches!(&*cache, Some((old, _)) if *old == values) {
+            return Some(false);
+        }
+        let val = (self.f)(values.clone());
+        *cache = Some((values, val));
+        Some(true)
+    }
+    /// `None` if any of the input sensors is gone.
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        self.recompute()?;
+        self.cache.borrow().as_ref().map(|(_, val)| f(val))
+    }
+    pub fn get(&self) -> Option<T>
+    where
+        T: Clone,
+    {
+        self.with(T::clone)
+    }
+}
+
+impl<T: AddAssign> AddAssign<T> for Sensor<T> {
+    fn add_assign(&mut self, rhs: T) {
+        self.update(|val| *val += rhs)
+    }
+}
+
+impl<T: MulAssign> MulAssign<T> for Sensor<T> {
+    fn mul_assign(&mut self, rhs: T) {
+        self.update(|val| *val *= rhs)
+    }
+}
+
+impl<T: SubAssign> SubAssign<T> for Sensor<T> {
+    fn sub_assign(&mut self, rhs: T) {
+        self.update(|val| *val -= rhs)
+    }
+}
+
+impl<T: RemAssign> RemAssign<T> for Sensor<T> {
+    fn rem_assign(&mut self, rhs: T) {
+        self.update(|val| *val %= rhs)
+    }
+}
+
+impl<T: PartialEq> PartialEq<T> for Sensor<T> {
+    fn eq(&self, other: &T) -> bool {
+        self.with(|val| val.eq(other))
+    }
+}
+
+impl<T: PartialOrd> PartialOrd<T> for Sensor<T> {
+    fn partial_cmp(&self, other: &T) -> Option<Ordering> {
+        self.with(|val| val.partial_cmp(other))
+    }
+}
+
+impl<T: Save> Save for Sensor<T> {
+    fn save(&self, key: impl Display) {
+        self.with(|val| val.save(key))
+    }
+
+    fn load(&mut self, key: impl Display) {
+        self.update(|val| val.load(key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsubscribe_while_notified() {
+        let (sensor, reader) = Sensor::new(0);
+        let calls = Rc::new(Cell::new(0));
+        let id = Rc::new(Cell::new(None));
+        let own_id = id.clone();
+        let own_calls = calls.clone();
+        id.set(Some(sensor.subscribe(move |_| {
+            own_calls.set(own_calls.get() + 1);
+            reader.unsubscribe(own_id.get().unwrap());
+        })));
+        let others = Rc::new(Cell::new(0));
+        let other_calls = others.clone();
+        sensor.subscribe(move |_| other_calls.set(other_calls.get() + 1));
+
+        sensor.set(1);
+        sensor.set(2);
+        assert_eq!(calls.get(), 1);
+        assert_eq!(others.get(), 2);
+    }
+
+    #[test]
+    fn unsubscribe_another_while_notified() {
+        let (sensor, reader) = Sensor::new(0);
+        let calls = Rc::new(Cell::new(0));
+        let later = Rc::new(Cell::new(None));
+        let first_later = later.clone();
+        sensor.subscribe(move |_| {
+            if let Some(id) = first_later.get() {
+                reader.unsubscribe(id);
+            }
+        });
+        let second_calls = calls.clone();
+        later.set(Some(
+            sensor.subscribe(move |_| second_calls.set(second_calls.get() + 1)),
+        ));
+
+        sensor.set(1);
+        sensor.set(2);
+        assert_eq!(calls.get(), 0);
+    }
+
+    #[test]
+    fn subscribe_while_notified() {
+        let (sensor, reader) = Sensor::new(0);
+        let calls = Rc::new(Cell::new(0));
+        let inner_calls = calls.clone();
+        let id = Rc::new(Cell::new(None));
+        let own_id = id.clone();
+        id.set(Some(sensor.subscribe(move |_| {
+            let inner_calls = inner_calls.clone();
+            reader.subscribe(move |_| inner_calls.set(inner_calls.get() + 1));
+            reader.unsubscribe(own_id.get().unwrap());
+        })));
+
+        sensor.set(1);
+        assert_eq!(calls.get(), 0);
+        sensor.set(2);
+        assert_eq!(calls.get(), 1);
+    }
+}
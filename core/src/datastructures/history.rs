@@ -0,0 +1,90 @@
+use std::collections::VecDeque;
+
+/// A value that can be tracked by a `History`.
+pub trait Sample: Copy + PartialOrd {
+    fn to_f64(self) -> f64;
+}
+
+macro_rules! sample {
+    ($($ty:ty),*) => {$(
+        impl Sample for $ty {
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+        }
+    )*};
+}
+
+sample!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+/// Timestamped values of a resource, so that rates can be computed from the actual
+/// time that passed instead of assuming a frame rate.
+pub struct History<T> {
+    samples: VecDeque<(f64, T)>,
+    /// Samples older than this many seconds are forgotten.
+    span: f64,
+}
+
+impl<T: Sample> History<T> {
+    pub fn new(span: f64) -> Self {
+        Self {
+            samples: VecDeque::new(),
+            span,
+        }
+    }
+
+    /// Record `val` at `now` seconds. Times must not go backwards.
+    pub fn record(&mut self, now: f64, val: T) {
+        self.samples.push_back((now, val));
+        while let Some(&(t, _)) = self.samples.front() {
+            if now - t <= self.span {
+                break;
+            }
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn latest(&self) -> Option<T> {
+        self.samples.back().map(|&(_, val)| val)
+    }
+
+    /// The samples of the last `window` seconds.
+    fn window(&self, window: f64) -> impl Iterator<Item = (f64, T)> + '_ {
+        let end = self.samples.back().map_or(0.0, |&(t, _)| t);
+        self.samples
+            .iter()
+            .copied()
+            .skip_while(move |&(t, _)| end - t > window)
+    }
+
+    /// Change per second over the last `window` seconds. `None` until there are
+    /// samples at two different points in time.
+    pub fn rate(&self, window: f64) -> Option<f64> {
+        let (start, first) = self.window(window).next()?;
+        let &(end, last) = self.samples.back()?;
+        if end <= start {
+            return None;
+        }
+        Some((last.to_f64() - first.to_f64()) / (end - start))
+    }
+
+    /// Average of the samples in the last `window` seconds.
+    pub fn average(&self, window: f64) -> Option<f64> {
+        let (n, sum) = self
+            .window(window)
+            .fold((0, 0.0), |(n, sum), (_, val)| (n + 1, sum + val.to_f64()));
+        (n > 0).then(|| sum / n as f64)
+    }
+
+    pub fn min(&self, window: f64) -> Option<T> {
+        self.window(window)
+            .map(|(_, val)| val)
+            .reduce(|a, b| if b < a { b } else { a })
+    }
+
+    pub fn max(&self, window: f64) -> Option<T> {
+        self.window(window)
+            .map(|(_, val)| val)
+            .reduce(|a, b| if b > a { b } else { a })
+    }
+}
@@ -0,0 +1,266 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Debug, Display},
+    str::FromStr,
+};
+
+use hex2d::Coordinate;
+
+use crate::save::{self, Save};
+
+/// Width and height of a chunk, in tiles.
+const CHUNK: i32 = 16;
+
+type ChunkPos = (i32, i32);
+
+fn split(at: Coordinate) -> (ChunkPos, usize) {
+    let pos = (at.x.div_euclid(CHUNK), at.y.div_euclid(CHUNK));
+    let index = at.y.rem_euclid(CHUNK) * CHUNK + at.x.rem_euclid(CHUNK);
+    (pos, index as usize)
+}
+
+fn join(pos: ChunkPos, index: usize) -> Coordinate {
+    let index = index as i32;
+    Coordinate::new(pos.0 * CHUNK + index % CHUNK, pos.1 * CHUNK + index / CHUNK)
+}
+
+/// Values on some of the tiles of an unbounded hex grid. Saved in square chunks, one key
+/// each, and only the chunks that changed since the last save get written again. The
+/// `Display` output of the values must not contain commas.
+#[derive(Clone)]
+pub struct SparseMap<T> {
+    chunks: BTreeMap<ChunkPos, Vec<Option<T>>>,
+    /// Chunks changed since the last save.
+    dirty: RefCell<BTreeSet<ChunkPos>>,
+    /// Whether chunks were added or removed since the last save.
+    reindex: Cell<bool>,
+}
+
+impl<T> Default for SparseMap<T> {
+    fn default() -> Self {
+        Self {
+            chunks: BTreeMap::new(),
+            dirty: RefCell::default(),
+            reindex: Cell::new(false),
+        }
+    }
+}
+
+impl<T> SparseMap<T> {
+    pub fn get(&self, at: Coordinate) -> Option<&T> {
+        let (pos, index) = split(at);
+        self.chunks.get(&pos)?[index].as_ref()
+    }
+
+    pub fn contains(&self, at: Coordinate) -> bool {
+        self.get(at).is_some()
+    }
+
+    /// Returns the value that was there before.
+    pub fn insert(&mut self, at: Coordinate, value: T) -> Option<T> {
+        let (pos, index) = split(at);
+        self.dirty.borrow_mut().insert(pos);
+        let reindex = &self.reindex;
+        let chunk = self.chunks.entry(pos).or_insert_with(|| {
+            reindex.set(true);
+            std::iter::repeat_with(|| None)
+                .take((CHUNK * CHUNK) as usize)
+                .collect()
+        });
+        chunk[index].replace(value)
+    }
+
+    pub fn remove(&mut self, at: Coordinate) -> Option<T> {
+        let (pos, index) = split(at);
+        let chunk = self.chunks.get_mut(&pos)?;
+        let value = chunk[index].take()?;
+        self.dirty.borrow_mut().insert(pos);
+        if chunk.iter().all(Option::is_none) {
+            self.chunks.remove(&pos);
+            self.reindex.set(true);
+        }
+        Some(value)
+    }
+
+    /// Remove every value. The emptied chunks are saved as such.
+    pub fn clear(&mut self) {
+        let mut dirty = self.dirty.borrow_mut();
+        dirty.extend(self.chunks.keys().copied());
+        if !self.chunks.is_empty() {
+            self.reindex.set(true);
+        }
+        self.chunks.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Coordinate, &T)> {
+        self.chunks.iter().flat_map(|(&pos, chunk)| {
+            chunk
+                .iter()
+                .enumerate()
+                .filter_map(move |(index, value)| Some((join(pos, index), value.as_ref()?)))
+        })
+    }
+}
+
+impl<T: PartialEq> PartialEq for SparseMap<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.chunks == other.chunks
+    }
+}
+
+impl<T: Debug> Debug for SparseMap<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Display + FromStr> Save for SparseMap<T>
+where
+    T::Err: Debug,
+{
+    fn save(&self, key: impl Display) {
+        for pos in std::mem::take(&mut *self.dirty.borrow_mut()) {
+            let blob = match self.chunks.get(&pos) {
+                Some(chunk) => {
+                    let len = chunk.iter().rposition(Option::is_some).map_or(0, |i| i + 1);
+                    let values: Vec<_> = chunk[..len]
+                        .iter()
+                        .map(|value| value.as_ref().map(T::to_string).unwrap_or_default())
+                        .collect();
+                    values.join(",")
+                }
+                // Storage can't forget keys, an empty chunk is as good.
+                None => String::new(),
+            };
+            save::save(format_args!("{}/{},{}", key, pos.0, pos.1), blob);
+        }
+        if self.reindex.replace(false) {
+            let index: Vec<_> = self
+                .chunks
+                .keys()
+                .map(|(x, y)| format!("{},{}", x, y))
+                .collect();
+            save::save(format_args!("{}/chunks", key), index.join(";"));
+        }
+    }
+
+    fn load(&mut self, key: impl Display) {
+        *self = Self::default();
+        let index: String = match save::load(format_args!("{}/chunks", key)) {
+            Some(index) => index,
+            None => return,
+        };
+        for pos in index.split(';').filter(|s| !s.is_empty()) {
+            let (x, y) = pos.split_once(',').unwrap();
+            let pos = (x.parse().unwrap(), y.parse().unwrap());
+            let blob: String =
+                save::load(format_args!("{}/{},{}", key, pos.0, pos.1)).unwrap_or_default();
+            let mut chunk: Vec<Option<T>> = blob
+                .split(',')
+                .map(|value| (!value.is_empty()).then(|| value.parse().unwrap()))
+                .collect();
+            chunk.resize_with((CHUNK * CHUNK) as usize, || None);
+            self.chunks.insert(pos, chunk);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved(key: &str) -> SparseMap<u64> {
+        let mut map = SparseMap::default();
+        map.load(key);
+        map
+    }
+
+    fn raw(key: &str) -> Option<String> {
+        save::load(key)
+    }
+
+    #[test]
+    fn round_trip() {
+        let _root = save::init_temp("sparse-round-trip");
+        let mut map = SparseMap::default();
+        for (x, y, value) in [
+            (0, 0, 1),
+            (15, 15, 2),
+            (16, 0, 3),
+            (-1, -1, 4),
+            (-17, 40, 5),
+        ] {
+            map.insert(Coordinate::new(x, y), value);
+        }
+        save::transaction(|| map.save("map"));
+        assert_eq!(saved("map"), map);
+        assert_eq!(saved("map").get(Coordinate::new(-1, -1)), Some(&4));
+        assert_eq!(saved("map").get(Coordinate::new(-17, 40)), Some(&5));
+        assert_eq!(saved("map").iter().count(), 5);
+    }
+
+    #[test]
+    fn remove_to_empty() {
+        let _root = save::init_temp("sparse-remove");
+        let mut map = SparseMap::default();
+        map.insert(Coordinate::new(-3, 2), 1);
+        map.insert(Coordinate::new(20, 20), 2);
+        save::transaction(|| map.save("map"));
+        assert_eq!(map.remove(Coordinate::new(-3, 2)), Some(1));
+        assert_eq!(map.remove(Coordinate::new(-3, 2)), None);
+        save::transaction(|| map.save("map"));
+        assert_eq!(raw("map/-1,0").as_deref(), Some(""));
+        assert_eq!(raw("map/chunks").as_deref(), Some("1,1"));
+        assert_eq!(saved("map"), map);
+        assert_eq!(map.remove(Coordinate::new(20, 20)), Some(2));
+        assert!(map.is_empty());
+        save::transaction(|| map.save("map"));
+        assert!(saved("map").is_empty());
+    }
+
+    #[test]
+    fn clear() {
+        let _root = save::init_temp("sparse-clear");
+        let mut map = SparseMap::default();
+        map.insert(Coordinate::new(-30, -30), 1);
+        map.insert(Coordinate::new(5, 5), 2);
+        save::transaction(|| map.save("map"));
+        map.clear();
+        save::transaction(|| map.save("map"));
+        assert!(saved("map").is_empty());
+        map.insert(Coordinate::new(5, 6), 3);
+        save::transaction(|| map.save("map"));
+        assert_eq!(saved("map"), map);
+        assert_eq!(saved("map").get(Coordinate::new(5, 5)), None);
+    }
+
+    #[test]
+    fn only_dirty_chunks_are_written() {
+        let _root = save::init_temp("sparse-dirty");
+        let mut map = SparseMap::default();
+        map.insert(Coordinate::new(0, 0), 1);
+        map.insert(Coordinate::new(-16, 0), 2);
+        save::transaction(|| map.save("map"));
+        // Tamper with what's saved, anything the next save leaves alone keeps it.
+        save::transaction(|| {
+            save::save("map/-1,0", "sentinel");
+            save::save("map/chunks", "sentinel");
+        });
+        map.insert(Coordinate::new(1, 0), 3);
+        save::transaction(|| map.save("map"));
+        assert_eq!(raw("map/0,0").as_deref(), Some("1,3"));
+        assert_eq!(raw("map/-1,0").as_deref(), Some("sentinel"));
+        assert_eq!(raw("map/chunks").as_deref(), Some("sentinel"));
+        // A new chunk rewrites the index, but still not the other chunks.
+        map.insert(Coordinate::new(0, -1), 4);
+        save::transaction(|| map.save("map"));
+        assert_eq!(raw("map/chunks").as_deref(), Some("-1,0;0,-1;0,0"));
+        assert_eq!(raw("map/-1,0").as_deref(), Some("sentinel"));
+        assert_eq!(raw("map/0,-1").map(|s| s.split(',').count()), Some(241));
+    }
+}
@@ -0,0 +1,210 @@
+use std::{
+    cmp::Ordering,
+    fmt::Display,
+    ops::*,
+    sync::{
+        atomic::{self, AtomicU64},
+        Arc, Mutex, Weak,
+    },
+};
+
+use super::Inputs;
+use crate::save::Save;
+
+/// Like `Sensor`, but can be shared across threads and coroutines: it and its readers are
+/// `Send + Sync` whenever `T: Send`.
+pub struct SyncSensor<T> {
+    input: Arc<Shared<T>>,
+}
+
+struct Shared<T> {
+    value: Mutex<T>,
+    version: AtomicU64,
+}
+
+impl<T: Copy> SyncSensor<T> {
+    /// Just create a sensor, leaving reader creation to later.
+    pub fn raw(val: T) -> Self {
+        let input = Arc::new(Shared {
+            value: Mutex::new(val),
+            version: AtomicU64::new(0),
+        });
+        Self { input }
+    }
+    /// Create a sensor and a reader at the same time
+    pub fn new(val: T) -> (Self, SyncReader<T>) {
+        let this = Self::raw(val);
+        let reader = this.make_reader();
+        (this, reader)
+    }
+    pub fn make_reader(&self) -> SyncReader<T> {
+        let output = Arc::downgrade(&self.input);
+        SyncReader {
+            output,
+            seen: AtomicU64::new(self.version()),
+        }
+    }
+    pub fn set(&self, val: T) {
+        self.update(|v| *v = val)
+    }
+    pub fn update(&self, f: impl FnOnce(&mut T)) {
+        // Hold the lock, so concurrent updates don't get lost.
+        let mut val = self.input.value.lock().unwrap();
+        f(&mut val);
+        self.input.version.fetch_add(1, atomic::Ordering::Release);
+    }
+    pub fn modify(&self, f: impl FnOnce(T) -> T) -> T {
+        let mut new = self.get();
+        self.update(|val| {
+            new = f(*val);
+            *val = new;
+        });
+        new
+    }
+    pub fn get(&self) -> T {
+        *self.input.value.lock().unwrap()
+    }
+    /// Number of writes so far.
+    pub fn version(&self) -> u64 {
+        self.input.version.load(atomic::Ordering::Acquire)
+    }
+}
+
+pub struct SyncReader<T> {
+    output: Weak<Shared<T>>,
+    /// Sensor version at the last `changed` call.
+    seen: AtomicU64,
+}
+
+impl<T: Copy> SyncReader<T> {
+    pub fn get(&self) -> Option<T> {
+        self.output.upgrade().map(|o| *o.value.lock().unwrap())
+    }
+    /// Whether the sensor was written to since the last call (or since the reader was
+    /// created). Always `false` once the sensor is gone.
+    pub fn changed(&self) -> bool {
+        match self.output.upgrade() {
+            Some(o) => {
+                let version = o.version.load(atomic::Ordering::Acquire);
+                self.seen.swap(version, atomic::Ordering::Relaxed) != version
+            }
+            None => false,
+        }
+    }
+}
+
+impl<T: Copy> Clone for SyncReader<T> {
+    fn clone(&self) -> Self {
+        Self {
+            output: self.output.clone(),
+            seen: AtomicU64::new(self.seen.load(atomic::Ordering::Relaxed)),
+        }
+    }
+}
+
+impl<T: Copy + PartialEq> Inputs for SyncReader<T> {
+    type Values = T;
+    fn read(&self) -> Option<T> {
+        self.get()
+    }
+}
+
+impl<T: Copy + AddAssign> AddAssign<T> for SyncSensor<T> {
+    fn add_assign(&mut self, rhs: T) {
+        self.update(|val| *val += rhs)
+    }
+}
+
+impl<T: Copy + MulAssign> MulAssign<T> for SyncSensor<T> {
+    fn mul_assign(&mut self, rhs: T) {
+        self.update(|val| *val *= rhs)
+    }
+}
+
+impl<T: Copy + SubAssign> SubAssign<T> for SyncSensor<T> {
+    fn sub_assign(&mut self, rhs: T) {
+        self.update(|val| *val -= rhs)
+    }
+}
+
+impl<T: Copy + RemAssign> RemAssign<T> for SyncSensor<T> {
+    fn rem_assign(&mut self, rhs: T) {
+        self.update(|val| *val %= rhs)
+    }
+}
+
+impl<T: Copy + PartialEq> PartialEq<T> for SyncSensor<T> {
+    fn eq(&self, other: &T) -> bool {
+        self.get().eq(other)
+    }
+}
+
+impl<T: Copy + PartialOrd> PartialOrd<T> for SyncSensor<T> {
+    fn partial_cmp(&self, other: &T) -> Option<Ordering> {
+        self.get().partial_cmp(other)
+    }
+}
+
+impl<T: Copy + Save> Save for SyncSensor<T> {
+    fn save(&self, key: impl Display) {
+        self.get().save(key)
+    }
+
+    fn load(&mut self, key: impl Display) {
+        self.update(|val| val.load(key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn shared_across_threads() {
+        assert_send_sync::<SyncSensor<u64>>();
+        assert_send_sync::<SyncReader<u64>>();
+
+        let sensor = Arc::new(SyncSensor::raw(0_u64));
+        let reader = sensor.make_reader();
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let sensor = sensor.clone();
+                thread::spawn(move || {
+                    for _ in 0..1000 {
+                        sensor.update(|val| *val += 1);
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(sensor.get(), 4000);
+        assert_eq!(sensor.version(), 4000);
+
+        let changed = thread::spawn(move || (reader.changed(), reader.changed(), reader.get()));
+        assert_eq!(changed.join().unwrap(), (true, false, Some(4000)));
+    }
+
+    #[test]
+    fn operators() {
+        let (mut sensor, reader) = SyncSensor::new(10_u64);
+        sensor += 5;
+        sensor *= 3;
+        sensor -= 1;
+        sensor %= 10;
+        assert_eq!(sensor.get(), 4);
+        assert!(sensor == 4);
+        assert!(sensor > 3 && sensor < 5);
+        assert_eq!(sensor.modify(|val| val * 2), 8);
+        assert!(reader.changed());
+        assert_eq!(reader.get(), Some(8));
+        drop(sensor);
+        assert_eq!(reader.get(), None);
+        assert!(!reader.changed());
+    }
+}
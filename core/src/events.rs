@@ -0,0 +1,80 @@
+//! Things that happen to the farm now and then, which the player can respond to.
+
+use crate::{
+    balance::Balance,
+    resources::{Cost, Resource::*},
+};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum EventKind {
+    /// A fox is after some of the chickens.
+    FoxRaid,
+    /// A storm is about to blow away some of the nests.
+    Storm,
+    /// Some of the chicks that ran away are back as grown-ups.
+    FlockReturns,
+}
+
+impl EventKind {
+    pub const ALL: [EventKind; 3] = [
+        EventKind::FoxRaid,
+        EventKind::Storm,
+        EventKind::FlockReturns,
+    ];
+
+    pub(crate) fn key(self) -> &'static str {
+        match self {
+            EventKind::FoxRaid => "fox_raid",
+            EventKind::Storm => "storm",
+            EventKind::FlockReturns => "flock_returns",
+        }
+    }
+
+    pub(crate) fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|kind| kind.key() == key)
+    }
+}
+
+/// An event waiting for the player's response.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Event {
+    pub kind: EventKind,
+    /// Chickens, nests or runaways at stake.
+    pub amount: u64,
+    /// Steps until the event runs its course without a response.
+    pub remaining: u64,
+}
+
+impl Event {
+    /// The headline shown while the event waits for a response, with what is at stake.
+    pub fn describe(&self) -> String {
+        match self.kind {
+            EventKind::FoxRaid => format!("A fox is after {} chickens!", self.amount),
+            EventKind::Storm => format!("A storm threatens {} nests!", self.amount),
+            EventKind::FlockReturns => {
+                format!("{} runaways came back and want in.", self.amount)
+            }
+        }
+    }
+
+    /// What responding costs.
+    pub fn cost(&self, balance: &Balance) -> Cost {
+        match self.kind {
+            EventKind::FoxRaid => Cost(vec![(Roosters, self.amount * balance.fox_roosters)]),
+            EventKind::Storm => Cost(vec![(Corn, self.amount * balance.shore_up_corn)]),
+            EventKind::FlockReturns => Cost(vec![(Corn, self.amount * balance.welcome_corn)]),
+        }
+    }
+
+    /// The label of the response.
+    pub fn response(&self, balance: &Balance) -> String {
+        let cost = self.cost(balance);
+        let (r, n) = cost.0[0];
+        let what = match self.kind {
+            EventKind::FoxRaid => "Chase the fox off",
+            EventKind::Storm => "Shore up the nests",
+            EventKind::FlockReturns => "Welcome them back",
+        };
+        format!("{} ({} {})", what, n, r.name())
+    }
+}
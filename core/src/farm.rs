@@ -0,0 +1,674 @@
+use std::{
+    fmt,
+    ops::{Index, IndexMut},
+    str::FromStr,
+};
+
+use crate::{
+    balance::Balance,
+    clock::TICKS_PER_SECOND,
+    events::{Event, EventKind},
+    recipes::Producer,
+    resources::Resource::{self, *},
+    rng::Rng,
+    State,
+};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum TaskKind {
+    /// The chicks hatched from `nests` nests at once.
+    GrowChicks {
+        nests: u64,
+    },
+    BuildNests,
+    FetchCorn,
+    /// The producer whose workers are these.
+    Produce(Resource),
+    /// The output of a recipe that takes a while.
+    Deliver(Resource),
+    /// What the buildings on the map produce.
+    Harvest,
+    /// The explorers' way into the fog.
+    Explore,
+    /// Waiting for the player's response.
+    Event(EventKind),
+}
+
+/// Work in progress, saved so that it resumes where it left off after a restart.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Task {
+    pub kind: TaskKind,
+    /// Steps until the chicks are grown up or the delivery arrives, or work left in
+    /// the current round.
+    pub remaining: u64,
+    /// Chicks that haven't run away yet, how much gets delivered, or what's at stake.
+    pub amount: u64,
+}
+
+impl Task {
+    pub fn new(kind: TaskKind, balance: &Balance) -> Self {
+        match kind {
+            TaskKind::GrowChicks { nests } => Self {
+                kind,
+                remaining: balance.growing_up_ticks,
+                amount: nests * balance.chicks_per_nest,
+            },
+            TaskKind::BuildNests | TaskKind::FetchCorn | TaskKind::Harvest => Self {
+                kind,
+                remaining: balance.round,
+                amount: 0,
+            },
+            TaskKind::Explore => Self {
+                kind,
+                remaining: balance.explore_work,
+                amount: 0,
+            },
+            TaskKind::Event(_) => Self {
+                kind,
+                remaining: balance.event_ticks,
+                amount: 0,
+            },
+            TaskKind::Produce(_) | TaskKind::Deliver(_) => Self {
+                kind,
+                remaining: 1,
+                amount: 0,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Task {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            TaskKind::GrowChicks { nests } => write!(f, "grow_chicks:{}", nests)?,
+            TaskKind::BuildNests => write!(f, "build_nests")?,
+            TaskKind::FetchCorn => write!(f, "fetch_corn")?,
+            TaskKind::Harvest => write!(f, "harvest")?,
+            TaskKind::Explore => write!(f, "explore")?,
+            TaskKind::Produce(workers) => write!(f, "produce:{}", workers.key())?,
+            TaskKind::Deliver(resource) => write!(f, "deliver:{}", resource.key())?,
+            TaskKind::Event(kind) => write!(f, "event:{}", kind.key())?,
+        }
+        write!(f, " {} {}", self.remaining, self.amount)
+    }
+}
+
+impl FromStr for Task {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let resource =
+            |key| Resource::from_key(key).ok_or_else(|| format!("unknown resource in `{}`", s));
+        let mut parts = s.split(' ');
+        let mut next = || {
+            parts
+                .next()
+                .ok_or_else(|| format!("incomplete task `{}`", s))
+        };
+        let kind = match next()? {
+            "build_nests" => TaskKind::BuildNests,
+            "fetch_corn" => TaskKind::FetchCorn,
+            "harvest" => TaskKind::Harvest,
+            "explore" => TaskKind::Explore,
+            kind => match kind.split_once(':') {
+                Some(("grow_chicks", nests)) => TaskKind::GrowChicks {
+                    nests: nests.parse().map_err(|e| format!("{}: {}", s, e))?,
+                },
+                Some(("produce", key)) => TaskKind::Produce(resource(key)?),
+                Some(("deliver", key)) => TaskKind::Deliver(resource(key)?),
+                Some(("event", key)) => TaskKind::Event(
+                    EventKind::from_key(key).ok_or_else(|| format!("unknown event `{}`", s))?,
+                ),
+                _ => return Err(format!("unknown task `{}`", s)),
+            },
+        };
+        let remaining = next()?.parse().map_err(|e| format!("{}: {}", s, e))?;
+        let amount = next()?.parse().map_err(|e| format!("{}: {}", s, e))?;
+        Ok(Self {
+            kind,
+            remaining,
+            amount,
+        })
+    }
+}
+
+/// All tasks, saved under a single key.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Tasks(pub Vec<Task>);
+
+impl fmt::Display for Tasks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, task) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ";")?;
+            }
+            write!(f, "{}", task)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Tasks {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(';')
+            .filter(|s| !s.is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+/// Tiles revealed at most by a single `Farm::store`, so a long absence can't uncover
+/// half the world at once.
+const MAX_REVEALS: u64 = 1000;
+
+/// The event among `tasks` that waits for a response, if there is one.
+pub fn find_event(tasks: &[Task]) -> Option<Event> {
+    tasks.iter().find_map(|task| match task.kind {
+        TaskKind::Event(kind) => Some(Event {
+            kind,
+            amount: task.amount,
+            remaining: task.remaining,
+        }),
+        _ => None,
+    })
+}
+
+/// The simulated part of the `State` as plain numbers, so that a simulation step only
+/// writes the values that actually changed to storage.
+#[derive(Clone)]
+pub struct Farm {
+    amounts: [u64; Resource::COUNT],
+    pub tasks: Vec<Task>,
+    pub balance: Balance,
+    pub producers: Vec<Producer>,
+    /// What the map yields per round.
+    pub harvest: Vec<(Resource, u64)>,
+    /// Steps to the nearest corn field, if there is one.
+    pub fetch_distance: Option<u64>,
+    /// Tiles the explorers have reached, revealed once the farm is stored.
+    pub explored: u64,
+    /// Achievements not reached yet, by index into `State::achievements`.
+    pub goals: Vec<(usize, (Resource, u64))>,
+    /// Achievements reached since `new`, and after how many steps.
+    pub reached: Vec<(usize, u64)>,
+    /// Steps simulated since `new`.
+    pub steps: u64,
+    pub rng: Rng,
+}
+
+impl Index<Resource> for Farm {
+    type Output = u64;
+
+    fn index(&self, resource: Resource) -> &u64 {
+        &self.amounts[resource as usize]
+    }
+}
+
+impl IndexMut<Resource> for Farm {
+    fn index_mut(&mut self, resource: Resource) -> &mut u64 {
+        &mut self.amounts[resource as usize]
+    }
+}
+
+impl Farm {
+    pub fn new(state: &State) -> Self {
+        let mut amounts = [0; Resource::COUNT];
+        for (resource, amount) in state.stock.iter() {
+            amounts[resource as usize] = amount;
+        }
+        Self {
+            amounts,
+            tasks: state.tasks.0.clone(),
+            balance: state.balance,
+            producers: state.producers.clone(),
+            harvest: state.map.yields(&state.balance, &state.land),
+            fetch_distance: state.route.as_ref().map(|route| route.cost),
+            explored: 0,
+            goals: state
+                .achievements
+                .iter()
+                .enumerate()
+                .filter(|(_, achievement)| !state.unlocked.contains(achievement.name))
+                .map(|(i, achievement)| (i, achievement.goal))
+                .collect(),
+            reached: vec![],
+            steps: 0,
+            rng: *state.rng,
+        }
+    }
+
+    pub fn store(self, state: &mut State) {
+        for &resource in Resource::ALL {
+            state.stock.set(resource, self[resource]);
+        }
+        if state.tasks.0 != self.tasks {
+            state.tasks.set(Tasks(self.tasks));
+        }
+        if *state.rng != self.rng {
+            state.rng.set(self.rng);
+        }
+        if self.explored > 0 {
+            for _ in 0..self.explored.min(MAX_REVEALS) {
+                match state.land.frontier() {
+                    Some(at) => state.land.reveal(at),
+                    // Surrounded by the already explored.
+                    None => break,
+                }
+            }
+            state.update_route();
+        }
+        let reached = self.reached;
+        if !reached.is_empty() {
+            // `last_played` is when the simulated time started.
+            let start = *state.last_played;
+            let achievements = &state.achievements;
+            state.unlocked.update(|unlocked| {
+                for (i, steps) in reached {
+                    let time = start + steps as f64 / TICKS_PER_SECOND as f64;
+                    unlocked.0.push((achievements[i].name.to_owned(), time));
+                }
+            });
+        }
+    }
+
+    /// Start nest building or corn fetching, unless it's already going on.
+    pub fn start(&mut self, kind: TaskKind) {
+        if !self.tasks.iter().any(|task| task.kind == kind) {
+            self.tasks.push(Task::new(kind, &self.balance));
+        }
+    }
+
+    /// Put every producer to work, unless it already is.
+    pub fn start_producers(&mut self) {
+        for producer in &self.producers {
+            let kind = TaskKind::Produce(producer.workers);
+            if !self.tasks.iter().any(|task| task.kind == kind) {
+                self.tasks.push(Task {
+                    kind,
+                    remaining: producer.interval,
+                    amount: 0,
+                });
+            }
+        }
+    }
+
+    /// Saves from before tasks were saved only know how many chicks there are. Let
+    /// them grow up from scratch, the stragglers run away.
+    pub fn adopt_untracked_chicks(&mut self) {
+        let tracked: u64 = self
+            .tasks
+            .iter()
+            .filter(|task| matches!(task.kind, TaskKind::GrowChicks { .. }))
+            .map(|task| task.amount)
+            .sum();
+        let untracked = self[Chicks].saturating_sub(tracked);
+        let per_nest = self.balance.chicks_per_nest;
+        let c = untracked % per_nest;
+        self[Chicks] -= c;
+        self[Runaway] += c;
+        if untracked >= per_nest {
+            let kind = TaskKind::GrowChicks {
+                nests: untracked / per_nest,
+            };
+            self.tasks.push(Task::new(kind, &self.balance));
+        }
+    }
+
+    /// One fixed simulation step.
+    pub fn step(&mut self) {
+        let batch = self.balance.breeding_per_batch;
+        // Only as many nests hatch as there are, like in `offline::fast_forward`.
+        let n = (self[Breeding] / batch).min(self[Nests]);
+        if self[Breeding] > batch && n > 0 {
+            self[Breeding] -= batch * n;
+            self.gain(Chicks, n.saturating_mul(self.balance.chicks_per_nest));
+            let task = Task::new(TaskKind::GrowChicks { nests: n }, &self.balance);
+            self.tasks.push(task);
+            self[Nests] -= n;
+        }
+
+        let mut tasks = std::mem::take(&mut self.tasks);
+        tasks.retain_mut(|task| !self.advance(task));
+        self.tasks = tasks;
+        self.steps += 1;
+        self.check_goals();
+    }
+
+    /// Move the achievements whose goal is met from `goals` to `reached`.
+    pub fn check_goals(&mut self) {
+        let mut goals = std::mem::take(&mut self.goals);
+        goals.retain(|&(i, (resource, amount))| {
+            let met = self[resource] >= amount;
+            if met {
+                self.reached.push((i, self.steps));
+            }
+            !met
+        });
+        self.goals = goals;
+    }
+
+    /// Advance `task` by one step. Returns whether it is done.
+    pub fn advance(&mut self, task: &mut Task) -> bool {
+        match task.kind {
+            TaskKind::GrowChicks { nests } => {
+                // Saved tasks may have more left than a changed balance allows.
+                let i = self.balance.growing_up_ticks.saturating_sub(task.remaining);
+                let remove = self.balance.chicks_running_away(nests, i);
+                if task.amount >= remove {
+                    self[Runaway] += remove;
+                    self[Chicks] -= remove;
+                    task.amount -= remove;
+                }
+                task.remaining -= 1;
+                if task.remaining > 0 {
+                    return false;
+                }
+                let chicks = task.amount;
+                self[Chicks] -= chicks;
+                let half = chicks / 2;
+                let rem = chicks % 2;
+                self[Chickens] += half;
+                self[Roosters] += half + rem;
+                true
+            }
+            TaskKind::BuildNests => {
+                task.remaining = task.remaining.saturating_sub(self[NestBuilders]);
+                if task.remaining == 0 {
+                    self.build_nests();
+                    task.remaining = self.balance.round;
+                }
+                false
+            }
+            TaskKind::FetchCorn => {
+                let groups = self[CornFetchers] / self.balance.fetchers_per_trip;
+                task.remaining = task.remaining.saturating_sub(groups);
+                if task.remaining == 0 {
+                    self.fetch_corn();
+                    task.remaining = self.fetch_round();
+                }
+                false
+            }
+            TaskKind::Produce(workers) => match self.producer(workers) {
+                Some(i) => {
+                    task.remaining -= 1;
+                    if task.remaining == 0 {
+                        self.produce(i, 1);
+                        task.remaining = self.producers[i].interval;
+                    }
+                    false
+                }
+                // Not produced anymore.
+                None => true,
+            },
+            TaskKind::Deliver(resource) => {
+                task.remaining -= 1;
+                if task.remaining > 0 {
+                    return false;
+                }
+                self[resource] += task.amount;
+                true
+            }
+            TaskKind::Harvest => {
+                task.remaining -= 1;
+                if task.remaining == 0 {
+                    self.harvest(1);
+                    task.remaining = self.balance.round;
+                }
+                false
+            }
+            TaskKind::Explore => {
+                task.remaining = task.remaining.saturating_sub(self[Explorers]);
+                if task.remaining == 0 {
+                    self.explored += 1;
+                    task.remaining = self.balance.explore_work;
+                }
+                false
+            }
+            TaskKind::Event(kind) => {
+                task.remaining -= 1;
+                if task.remaining > 0 {
+                    return false;
+                }
+                self.settle(kind, task.amount, false);
+                true
+            }
+        }
+    }
+
+    /// Maybe start an event, unless one is waiting for a response already. Always
+    /// draws the same amount of random numbers, so the same seed brings the same events.
+    pub fn roll_event(&mut self) {
+        let happens = self.rng.below(self.balance.event_chance) == 0;
+        let kind = EventKind::ALL[self.rng.below(EventKind::ALL.len() as u64) as usize];
+        let roll = self.rng.next();
+        if !happens || find_event(&self.tasks).is_some() {
+            return;
+        }
+        let (pool, percent) = match kind {
+            EventKind::FoxRaid => (self[Chickens], self.balance.fox_percent),
+            EventKind::Storm => (self[Nests], self.balance.storm_percent),
+            EventKind::FlockReturns => (self[Runaway], self.balance.flock_percent),
+        };
+        let amount = pool * (1 + roll % percent) / 100;
+        if amount > 0 {
+            let mut task = Task::new(TaskKind::Event(kind), &self.balance);
+            task.amount = amount;
+            self.tasks.push(task);
+        }
+    }
+
+    /// Respond to the waiting event by paying for it, or let it run its course right
+    /// away. Returns whether there was an event and the response could be paid for.
+    pub fn respond(&mut self, respond: bool) -> bool {
+        let event = match find_event(&self.tasks) {
+            Some(event) => event,
+            None => return false,
+        };
+        if respond {
+            let cost = event.cost(&self.balance);
+            if cost.0.iter().any(|&(r, n)| self[r] < n) {
+                return false;
+            }
+            for &(r, n) in &cost.0 {
+                self[r] -= n;
+            }
+        }
+        self.tasks
+            .retain(|task| !matches!(task.kind, TaskKind::Event(_)));
+        self.settle(event.kind, event.amount, respond);
+        true
+    }
+
+    /// How an event ends, depending on whether a response was paid for.
+    pub fn settle(&mut self, kind: EventKind, amount: u64, responded: bool) {
+        match (kind, responded) {
+            (EventKind::FoxRaid, false) => self[Chickens] -= amount.min(self[Chickens]),
+            (EventKind::Storm, false) => self[Nests] -= amount.min(self[Nests]),
+            (EventKind::FlockReturns, true) => {
+                let n = amount.min(self[Runaway]);
+                self[Runaway] -= n;
+                self[Chickens] += n / 2;
+                self[Roosters] += n - n / 2;
+            }
+            // Chased off, shored up or shooed away.
+            _ => {}
+        }
+    }
+
+    /// The index of the producer employing `workers`.
+    pub fn producer(&self, workers: Resource) -> Option<usize> {
+        self.producers.iter().position(|p| p.workers == workers)
+    }
+
+    /// Add `n` to `resource`, stopping at `u64::MAX` instead of overflowing late in the
+    /// game.
+    pub fn gain(&mut self, resource: Resource, n: u64) {
+        self[resource] = self[resource].saturating_add(n);
+    }
+
+    /// `cycles` intervals of work by a producer, as far as its inputs last.
+    pub fn produce(&mut self, producer: usize, cycles: u64) {
+        let producer = &self.producers[producer];
+        let amounts = &mut self.amounts;
+        let mut runs = amounts[producer.workers as usize].saturating_mul(cycles);
+        for &(r, n) in &producer.inputs {
+            if n > 0 {
+                runs = runs.min(amounts[r as usize] / n);
+            }
+        }
+        for &(r, n) in &producer.inputs {
+            amounts[r as usize] -= n * runs;
+        }
+        for &(r, n) in &producer.outputs {
+            let amount = &mut amounts[r as usize];
+            *amount = amount.saturating_add(n.saturating_mul(runs));
+        }
+    }
+
+    /// `rounds` rounds of what the map yields.
+    pub fn harvest(&mut self, rounds: u64) {
+        for &(r, n) in &self.harvest {
+            let amount = &mut self.amounts[r as usize];
+            *amount = amount.saturating_add(n.saturating_mul(rounds));
+        }
+    }
+
+    /// One round of work by the nest builders.
+    pub fn build_nests(&mut self) {
+        let nb = self[NestBuilders]
+            .checked_sub(self.balance.round)
+            .unwrap_or(1);
+        let corn = self[Corn];
+        self[Nests] += nb;
+        if corn > nb {
+            self[Corn] -= nb;
+        } else {
+            self[Roosters] += nb - corn;
+            self[NestBuilders] -= nb - corn;
+            self[Corn] = 0;
+        }
+    }
+
+    /// Work for one round of corn fetching, including the way there and back.
+    pub fn fetch_round(&self) -> u64 {
+        let distance = self.fetch_distance.unwrap_or(self.balance.forage_distance);
+        self.balance.round + 2 * distance * self.balance.travel_per_step
+    }
+
+    /// One round of work by the corn fetchers.
+    pub fn fetch_corn(&mut self) {
+        let fetched = self[CornFetchers]
+            .checked_sub(self.balance.round)
+            .unwrap_or(1);
+        self.gain(Corn, fetched.saturating_mul(self.balance.corn_per_fetch));
+        self[Roosters] += fetched;
+        self[CornFetchers] -= fetched;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_farm() -> Farm {
+        Farm::new(&State::new(Balance::default()))
+    }
+
+    #[test]
+    fn gains_saturate() {
+        let mut farm = new_farm();
+        farm[Corn] = u64::MAX - 1;
+        farm.harvest = vec![(Corn, 10)];
+        farm.harvest(u64::MAX);
+        assert_eq!(farm[Corn], u64::MAX);
+
+        farm[Nests] = u64::MAX;
+        farm[Breeding] = u64::MAX - 5;
+        farm.produce(0, 2);
+        assert_eq!(farm[Breeding], u64::MAX);
+
+        let mut farm = new_farm();
+        farm[Nests] = 2;
+        farm[Breeding] = 2 * farm.balance.breeding_per_batch + 1;
+        farm[Chicks] = u64::MAX - 1;
+        farm.step();
+        // Minus the first few running away.
+        assert!(farm[Chicks] > u64::MAX - 2 * farm.balance.chicks_per_nest);
+        assert_eq!(farm[Nests], 0);
+
+        farm[CornFetchers] = u64::MAX;
+        farm.fetch_corn();
+        assert_eq!(farm[Corn], u64::MAX);
+    }
+
+    #[test]
+    fn tasks_round_trip() {
+        let kinds = [
+            TaskKind::GrowChicks { nests: 12 },
+            TaskKind::BuildNests,
+            TaskKind::FetchCorn,
+            TaskKind::Harvest,
+            TaskKind::Explore,
+            TaskKind::Produce(Nests),
+            TaskKind::Deliver(CornFetchers),
+            TaskKind::Event(EventKind::FoxRaid),
+            TaskKind::Event(EventKind::Storm),
+            TaskKind::Event(EventKind::FlockReturns),
+        ];
+        for kind in kinds {
+            // Fails to compile when a kind is added, so it gets added above as well.
+            match kind {
+                TaskKind::GrowChicks { .. }
+                | TaskKind::BuildNests
+                | TaskKind::FetchCorn
+                | TaskKind::Harvest
+                | TaskKind::Explore
+                | TaskKind::Produce(_)
+                | TaskKind::Deliver(_)
+                | TaskKind::Event(_) => {}
+            }
+            let task = Task {
+                kind,
+                remaining: 17,
+                amount: u64::MAX,
+            };
+            assert_eq!(task.to_string().parse(), Ok(task));
+        }
+        let tasks = Tasks(
+            kinds
+                .iter()
+                .enumerate()
+                .map(|(i, &kind)| Task {
+                    kind,
+                    remaining: i as u64,
+                    amount: 2 * i as u64,
+                })
+                .collect(),
+        );
+        assert_eq!(tasks.to_string().parse(), Ok(tasks));
+        assert_eq!("".parse(), Ok(Tasks::default()));
+    }
+
+    #[test]
+    fn malformed_tasks() {
+        for s in [
+            "",
+            "dig 1 2",
+            "grow_chicks 1 2",
+            "grow_chicks:x 1 2",
+            "produce:gold 1 2",
+            "deliver: 1 2",
+            "event:flood 1 2",
+            "harvest",
+            "harvest 1",
+            "harvest one 2",
+            "harvest 1 -2",
+        ] {
+            assert!(s.parse::<Task>().is_err(), "{:?}", s);
+        }
+        assert!("harvest 1 2;explore 3".parse::<Tasks>().is_err());
+    }
+}
@@ -0,0 +1,399 @@
+//! The rules of the game, independent of how (or whether) it is displayed.
+
+use achievements::{Achievement, Unlocked};
+use balance::Balance;
+use clock::Clock;
+use events::Event;
+use farm::{Farm, Task, TaskKind, Tasks};
+use hex2d::Coordinate;
+use map::{Building, Map};
+use path::Route;
+use recipes::{Producer, Recipe};
+use research::{Researched, Upgrade};
+use resources::{Cost, Resource::*, Stock};
+use rng::Rng;
+use save::Saveable;
+use terrain::{Land, Terrain, Visibility};
+
+pub mod achievements;
+pub mod balance;
+mod bigcount;
+mod clock;
+pub mod datastructures;
+pub mod events;
+mod farm;
+pub mod map;
+mod offline;
+pub mod path;
+pub mod recipes;
+pub mod research;
+pub mod resources;
+mod rng;
+pub mod save;
+pub mod terrain;
+
+pub use bigcount::BigCount;
+pub use clock::TICKS_PER_SECOND;
+pub use offline::Summary;
+
+/// Balance keys every golden feather increases.
+const FEATHERED: [&str; 4] = [
+    "chicks_per_nest",
+    "corn_per_fetch",
+    "corn_per_field",
+    "eggs_per_coop_nest",
+];
+
+pub struct State {
+    pub stock: Stock,
+    /// What the player can make, see `Action::Recipe`.
+    pub recipes: Vec<Recipe>,
+    pub(crate) producers: Vec<Producer>,
+    pub(crate) tasks: Saveable<Tasks>,
+    pub map: Saveable<Map>,
+    pub land: Land,
+    /// The way the corn fetchers take, if there's a corn field they can get to.
+    pub route: Option<Route>,
+    /// What the player can research, see `Action::Research`.
+    pub upgrades: Vec<Upgrade>,
+    pub researched: Saveable<Researched>,
+    pub achievements: Vec<Achievement>,
+    pub unlocked: Saveable<Unlocked>,
+    /// Earned by starting over, see `prestige`.
+    pub feathers: Saveable<u64>,
+    /// Decides when events happen, 0 until `resume` seeded it.
+    rng: Saveable<Rng>,
+    /// Unix time of the last committed frame.
+    pub last_played: Saveable<f64>,
+    /// With the researched upgrades applied.
+    pub balance: Balance,
+    /// Without any upgrades.
+    base: Balance,
+    clock: Clock,
+}
+
+impl State {
+    /// Load the last saved farm, or start a new one.
+    pub fn load(balance: Balance) -> Self {
+        Self::open(balance, true)
+    }
+
+    /// A new farm that is never saved, so it can be played without storage or
+    /// transactions, e.g. to simulate or test the rules.
+    pub fn new(balance: Balance) -> Self {
+        Self::open(balance, false)
+    }
+
+    fn open(balance: Balance, saved: bool) -> Self {
+        let key = |key: &'static str| saved.then(|| key);
+        let mut this = Self {
+            stock: if saved { Stock::load() } else { Stock::new() },
+            recipes: recipes::recipes(&balance),
+            producers: recipes::producers(),
+            tasks: Saveable::open(Tasks::default(), key("tasks")),
+            map: Saveable::open(Map::default(), key("map")),
+            land: if saved { Land::load() } else { Land::new() },
+            route: None,
+            last_played: Saveable::open(0.0, key("last_played")),
+            upgrades: research::upgrades(),
+            researched: Saveable::open(Researched::default(), key("researched")),
+            achievements: achievements::achievements(),
+            unlocked: Saveable::open(Unlocked::default(), key("achievements")),
+            feathers: Saveable::open(0_u64, key("feathers")),
+            rng: Saveable::open(Rng::default(), key("rng")),
+            balance,
+            base: balance,
+            clock: Clock::new(),
+        };
+        this.rebalance();
+        this
+    }
+
+    /// Apply the researched upgrades and the golden feathers to the balance again.
+    fn rebalance(&mut self) {
+        let mut modifiers: Vec<_> = self
+            .upgrades
+            .iter()
+            .filter(|upgrade| self.researched.contains(upgrade.name))
+            .flat_map(|upgrade| upgrade.modifiers.iter().copied())
+            .collect();
+        let percent = 100 + *self.feathers * self.base.feather_percent;
+        modifiers.extend(FEATHERED.iter().map(|&key| (key, percent)));
+        self.balance = self.base.modified(&modifiers);
+        self.recipes = recipes::recipes(&self.balance);
+    }
+
+    /// Find the nearest corn field again, after the map or the known land changed.
+    fn update_route(&mut self) {
+        let fields: Vec<_> = self
+            .land
+            .iter()
+            .filter(|&(at, terrain)| {
+                terrain == Terrain::CornField || self.map.get(at) == Some(Building::CornField)
+            })
+            .map(|(at, _)| at)
+            .collect();
+        self.route = path::find(&self.land, Coordinate::new(0, 0), &fields);
+    }
+
+    /// The recipes currently offered to the player.
+    fn offered(&self) -> impl Iterator<Item = &Recipe> + '_ {
+        self.recipes
+            .iter()
+            .filter(move |recipe| recipe.visible(&self.stock))
+    }
+}
+
+/// Repair what got lost when the game was closed and catch up on the time since.
+/// Must be called once before the first `tick`, in a transaction if the state is saved.
+pub fn resume(state: &mut State, now: f64) -> Option<Summary> {
+    state.land.sow(now.to_bits());
+    if state.rng.0 == 0 {
+        // The events follow from the same seed as the land.
+        state.rng.set(Rng(state.land.seed()));
+    }
+    if state.land.is_unexplored() {
+        let start = Coordinate::new(0, 0);
+        if state.map.is_empty() {
+            state.map.update(|map| {
+                map.place(start, Building::Coop);
+            });
+        }
+        // Farms from before the fog keep seeing what they built.
+        for (at, _) in state.map.iter() {
+            state.land.reveal(at);
+        }
+    }
+    state.update_route();
+    let mut farm = Farm::new(state);
+    farm.adopt_untracked_chicks();
+    farm.start_producers();
+    farm.start(TaskKind::Harvest);
+    if farm[NestBuilders] > 0 {
+        farm.start(TaskKind::BuildNests);
+    }
+    if farm[Explorers] > 0 {
+        farm.start(TaskKind::Explore);
+    }
+    farm.store(state);
+    let away = now - *state.last_played;
+    (*state.last_played > 0.0 && away >= 60.0).then(|| offline::catch_up(state, away))
+}
+
+/// Let `dt` seconds pass. Returns how many fixed simulation steps that amounted to.
+pub fn tick(state: &mut State, dt: f64) -> u64 {
+    let steps = state.clock.advance(dt);
+    let mut farm = Farm::new(state);
+    for kind in state.offered().filter_map(|recipe| recipe.starts) {
+        farm.start(kind);
+    }
+    for _ in 0..steps {
+        farm.step();
+        farm.roll_event();
+    }
+    farm.store(state);
+    steps
+}
+
+/// The event waiting for the player's response, if there is one.
+pub fn event(state: &State) -> Option<Event> {
+    farm::find_event(&state.tasks.0)
+}
+
+/// Golden feathers starting over would earn right now.
+pub fn prestige_reward(state: &State) -> u64 {
+    let n = state.stock.get(Chickens) / state.balance.feathers_divisor;
+    let mut root = (n as f64).sqrt() as u64;
+    // Float rounding can be off by one either way.
+    while root * root > n {
+        root -= 1;
+    }
+    while (root + 1) * (root + 1) <= n {
+        root += 1;
+    }
+    root
+}
+
+/// Start over with a single chicken for the golden feathers of `prestige_reward`.
+/// The farm, its tasks and its research are gone, the land stays explored. A saved
+/// state must be reset in a transaction, so that a crash never leaves a half reset farm
+/// behind.
+/// Returns whether the farm was reset.
+pub fn prestige(state: &mut State) -> bool {
+    let reward = prestige_reward(state);
+    if reward == 0 {
+        return false;
+    }
+    state.stock.reset();
+    state.tasks.set(Tasks::default());
+    state.map.update(|map| {
+        map.clear();
+        map.place(Coordinate::new(0, 0), Building::Coop);
+    });
+    state.researched.set(Researched::default());
+    state.feathers.update(|feathers| *feathers += reward);
+    state.rebalance();
+    state.update_route();
+    let mut farm = Farm::new(state);
+    farm.start_producers();
+    farm.start(TaskKind::Harvest);
+    farm.store(state);
+    true
+}
+
+/// Whether the player gets to see the map.
+pub fn map_unlocked(state: &State) -> bool {
+    state.offered().any(|recipe| recipe.shows_map) || state.land.revealed() > 1
+}
+
+/// Build `building` on the tile `at` if it's revealed, buildable, free and affordable.
+/// Returns whether it was built.
+pub fn place(state: &mut State, at: Coordinate, building: Building) -> bool {
+    if !state.land.buildable(at) || state.map.get(at).is_some() {
+        return false;
+    }
+    if !state.stock.try_spend(&building.cost(&state.balance)) {
+        return false;
+    }
+    state.map.update(|map| {
+        map.place(at, building);
+    });
+    state.update_route();
+    true
+}
+
+/// Pay for revealing the glimpsed tile `at`. Returns whether it was revealed.
+pub fn scout(state: &mut State, at: Coordinate) -> bool {
+    if state.land.visibility(at) != Some(Visibility::Glimpsed) {
+        return false;
+    }
+    if !state.stock.try_spend(&scouting_cost(state)) {
+        return false;
+    }
+    state.land.reveal(at);
+    state.update_route();
+    true
+}
+
+/// What revealing a tile with `scout` costs.
+pub fn scouting_cost(state: &State) -> Cost {
+    Cost(vec![(Corn, state.balance.scout_cost)])
+}
+
+/// Something the player can do.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Action {
+    /// Follow the recipe at this index of `State::recipes`.
+    Recipe(usize),
+    LayEgg,
+    /// Research the upgrade at this index of `State::upgrades`.
+    Research(usize),
+    /// See `prestige`.
+    Prestige,
+    /// Pay for responding to the waiting `event`, or let it run its course.
+    Respond(bool),
+}
+
+/// The actions the player currently gets to see, and whether they can be done right now.
+pub fn actions(state: &State) -> Vec<(Action, bool)> {
+    let mut actions: Vec<_> = state
+        .recipes
+        .iter()
+        .enumerate()
+        .filter(|(_, recipe)| recipe.visible(&state.stock))
+        .map(|(i, _)| Action::Recipe(i))
+        .collect();
+    if state.stock.get(Eggs) < state.balance.egg_cap {
+        actions.push(Action::LayEgg);
+    }
+    actions.extend(
+        state
+            .upgrades
+            .iter()
+            .enumerate()
+            .filter(|(_, upgrade)| upgrade.visible(&state.researched, &state.stock))
+            .map(|(i, _)| Action::Research(i)),
+    );
+    if prestige_reward(state) > 0 {
+        actions.push(Action::Prestige);
+    }
+    if event(state).is_some() {
+        actions.push(Action::Respond(true));
+        actions.push(Action::Respond(false));
+    }
+    actions
+        .into_iter()
+        .map(|action| (action, can_apply(state, action)))
+        .collect()
+}
+
+fn can_apply(state: &State, action: Action) -> bool {
+    match action {
+        Action::Recipe(i) => state.recipes.get(i).map_or(false, |recipe| {
+            recipe.visible(&state.stock) && recipe.affordable(&state.stock)
+        }),
+        Action::LayEgg => {
+            state.stock.get(Eggs) < state.balance.egg_cap
+                && state.stock.get(Chickens) > state.stock.get(Nests)
+        }
+        Action::Research(i) => state.upgrades.get(i).map_or(false, |upgrade| {
+            upgrade.researchable(&state.researched) && upgrade.cost.affordable(&state.stock)
+        }),
+        Action::Prestige => prestige_reward(state) > 0,
+        Action::Respond(respond) => event(state).map_or(false, |event| {
+            !respond || event.cost(&state.balance).affordable(&state.stock)
+        }),
+    }
+}
+
+/// Do `action` if it can be done right now. Returns whether it was done.
+pub fn apply(state: &mut State, action: Action) -> bool {
+    if !can_apply(state, action) {
+        return false;
+    }
+    match action {
+        Action::Recipe(i) => {
+            let recipe = &state.recipes[i];
+            if !state.stock.try_spend(&recipe.inputs) {
+                return false;
+            }
+            for &(resource, amount) in &recipe.outputs {
+                if recipe.duration == 0 {
+                    state.stock.add(resource, amount);
+                } else {
+                    state.tasks.update(|tasks| {
+                        tasks.0.push(Task {
+                            kind: TaskKind::Deliver(resource),
+                            remaining: recipe.duration,
+                            amount,
+                        })
+                    });
+                }
+            }
+        }
+        Action::LayEgg => {
+            let free = state.stock.get(Chickens) - state.stock.get(Nests);
+            state.stock.add(Eggs, free);
+        }
+        Action::Research(i) => {
+            let upgrade = &state.upgrades[i];
+            if !state.stock.try_spend(&upgrade.cost) {
+                return false;
+            }
+            let name = upgrade.name.to_owned();
+            state
+                .researched
+                .update(|researched| researched.0.push(name));
+            state.rebalance();
+        }
+        Action::Prestige => return prestige(state),
+        Action::Respond(respond) => {
+            let mut farm = Farm::new(state);
+            if !farm.respond(respond) {
+                return false;
+            }
+            farm.store(state);
+        }
+    }
+    true
+}
@@ -0,0 +1,151 @@
+//! The farm's hex grid and what is built on it.
+
+use std::{fmt, str::FromStr};
+
+use hex2d::Coordinate;
+
+use crate::{
+    balance::Balance,
+    datastructures::SparseMap,
+    resources::{
+        Cost,
+        Resource::{self, *},
+    },
+    save::Save,
+    terrain::{Land, Terrain},
+};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Building {
+    /// Lets the coops next to it lay eggs.
+    Nest,
+    /// Lays eggs for every nest next to it.
+    Coop,
+    /// Grows corn, more if there are other fields around.
+    CornField,
+}
+
+impl Building {
+    pub const ALL: [Building; 3] = [Building::Nest, Building::Coop, Building::CornField];
+
+    /// The label on the build palette.
+    pub fn name(self) -> &'static str {
+        match self {
+            Building::Nest => "nest",
+            Building::Coop => "coop",
+            Building::CornField => "corn field",
+        }
+    }
+
+    fn key(self) -> &'static str {
+        match self {
+            Building::Nest => "nest",
+            Building::Coop => "coop",
+            Building::CornField => "corn_field",
+        }
+    }
+
+    pub fn cost(self, balance: &Balance) -> Cost {
+        match self {
+            Building::Nest => Cost(vec![(Eggs, balance.eggs_per_nest)]),
+            Building::Coop => Cost(vec![(Corn, balance.coop_cost)]),
+            Building::CornField => Cost(vec![(Roosters, balance.field_cost)]),
+        }
+    }
+}
+
+impl fmt::Display for Building {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.key())
+    }
+}
+
+impl FromStr for Building {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|b| b.key() == s)
+            .ok_or_else(|| format!("unknown building `{}`", s))
+    }
+}
+
+/// Everything built on the farm.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Map {
+    buildings: SparseMap<Building>,
+}
+
+impl Map {
+    pub fn get(&self, at: Coordinate) -> Option<Building> {
+        self.buildings.get(at).copied()
+    }
+
+    /// Put `building` on `at`, unless something is there already. Returns whether it
+    /// was built.
+    pub fn place(&mut self, at: Coordinate, building: Building) -> bool {
+        if self.buildings.contains(at) {
+            return false;
+        }
+        self.buildings.insert(at, building);
+        true
+    }
+
+    /// Tear everything down.
+    pub fn clear(&mut self) {
+        self.buildings.clear()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Coordinate, Building)> + '_ {
+        self.buildings.iter().map(|(at, &building)| (at, building))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buildings.is_empty()
+    }
+
+    /// How many of the tiles around `at` have `building` on them.
+    fn neighbors(&self, at: Coordinate, building: Building) -> u64 {
+        at.neighbors()
+            .iter()
+            .filter(|&&n| self.get(n) == Some(building))
+            .count() as u64
+    }
+
+    /// What the buildings produce per round, depending on what is next to them and what
+    /// they are built on.
+    pub fn yields(&self, balance: &Balance, land: &Land) -> Vec<(Resource, u64)> {
+        let mut eggs = 0;
+        let mut corn = 0;
+        for (at, building) in self.iter() {
+            match building {
+                Building::Nest => {}
+                Building::Coop => {
+                    eggs += balance.eggs_per_coop_nest * self.neighbors(at, Building::Nest);
+                }
+                Building::CornField => {
+                    let fields = 1 + self.neighbors(at, Building::CornField);
+                    let wild = if land.get(at) == Some(Terrain::CornField) {
+                        2
+                    } else {
+                        1
+                    };
+                    corn += balance.corn_per_field * fields * wild;
+                }
+            }
+        }
+        vec![(Eggs, eggs), (Corn, corn)]
+    }
+}
+
+impl Save for Map {
+    fn save(&self, key: impl fmt::Display) {
+        self.buildings.save(key)
+    }
+
+    fn load(&mut self, key: impl fmt::Display) {
+        self.buildings.load(key)
+    }
+}
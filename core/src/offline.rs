@@ -0,0 +1,254 @@
+use std::cmp::Ordering;
+
+use crate::{
+    clock::TICKS_PER_SECOND,
+    farm::{Farm, Task, TaskKind},
+    resources::Resource::{self, *},
+    State,
+};
+
+/// Longer absences only count this long.
+const MAX_OFFLINE_SECONDS: f64 = 7.0 * 24.0 * 60.0 * 60.0;
+/// Long absences are simulated in coarser steps instead of more steps.
+const MAX_CHUNKS: u64 = 10_000;
+
+/// `rounds` rounds of `Farm::build_nests` at once.
+fn build_nests(farm: &mut Farm, mut rounds: u64) {
+    while rounds > 0 && farm[NestBuilders] > 0 {
+        let nb = farm[NestBuilders]
+            .checked_sub(farm.balance.round)
+            .unwrap_or(1);
+        if nb == 0 {
+            break;
+        }
+        // As long as there's enough corn, every round is the same.
+        let fed = (farm[Corn].saturating_sub(1) / nb).min(rounds);
+        if fed > 0 {
+            farm.gain(Nests, nb * fed);
+            farm[Corn] -= nb * fed;
+            rounds -= fed;
+        } else {
+            farm.build_nests();
+            rounds -= 1;
+        }
+    }
+}
+
+/// Roughly the same as `ticks` calls to `Farm::step`, but without looking at every
+/// single step.
+fn fast_forward(farm: &mut Farm, ticks: u64) {
+    let balance = farm.balance;
+    let n = (farm[Breeding] / balance.breeding_per_batch).min(farm[Nests]);
+    if n > 0 {
+        farm[Breeding] -= balance.breeding_per_batch * n;
+        farm[Nests] -= n;
+        farm[Chicks] += n * balance.chicks_per_nest;
+        let task = Task::new(TaskKind::GrowChicks { nests: n }, &balance);
+        farm.tasks.push(task);
+    }
+    let round = balance.round;
+
+    let mut tasks = std::mem::take(&mut farm.tasks);
+    tasks.retain_mut(|task| match task.kind {
+        TaskKind::GrowChicks { .. } => !(0..ticks).any(|_| farm.advance(task)),
+        TaskKind::BuildNests => {
+            // Saved tasks may have more left than a changed balance allows.
+            let work = round.saturating_sub(task.remaining) + farm[NestBuilders] * ticks;
+            build_nests(farm, (work / round).min(ticks));
+            task.remaining = round - work % round;
+            true
+        }
+        TaskKind::FetchCorn => {
+            let round = farm.fetch_round();
+            let groups = farm[CornFetchers] / balance.fetchers_per_trip;
+            // The way may have gotten shorter since the round started.
+            let work = round.saturating_sub(task.remaining) + groups * ticks;
+            for _ in 0..(work / round).min(ticks) {
+                // Fetchers that are too few to make progress stay out there.
+                if farm[CornFetchers] < balance.fetchers_per_trip {
+                    break;
+                }
+                farm.fetch_corn();
+            }
+            task.remaining = round - work % round;
+            true
+        }
+        TaskKind::Produce(workers) => match farm.producer(workers) {
+            Some(i) => {
+                let interval = farm.producers[i].interval;
+                let done = interval.saturating_sub(task.remaining) + ticks;
+                farm.produce(i, done / interval);
+                task.remaining = interval - done % interval;
+                true
+            }
+            None => false,
+        },
+        TaskKind::Explore => {
+            let work = balance.explore_work;
+            let done = work.saturating_sub(task.remaining) + farm[Explorers] * ticks;
+            farm.explored += done / work;
+            task.remaining = work - done % work;
+            true
+        }
+        TaskKind::Harvest => {
+            let done = round.saturating_sub(task.remaining) + ticks;
+            farm.harvest(done / round);
+            task.remaining = round - done % round;
+            true
+        }
+        TaskKind::Deliver(resource) => {
+            if task.remaining > ticks {
+                task.remaining -= ticks;
+                return true;
+            }
+            farm[resource] += task.amount;
+            false
+        }
+        TaskKind::Event(kind) => {
+            if task.remaining > ticks {
+                task.remaining -= ticks;
+                return true;
+            }
+            farm.settle(kind, task.amount, false);
+            false
+        }
+    });
+    farm.tasks = tasks;
+    farm.steps += ticks;
+    farm.check_goals();
+}
+
+/// What happened while the game was closed.
+pub struct Summary {
+    pub seconds: f64,
+    before: Farm,
+    after: Farm,
+}
+
+impl Summary {
+    pub fn lines(&self) -> Vec<String> {
+        let minutes = (self.seconds / 60.0) as u64;
+        let mut lines = vec![format!(
+            "While you were away ({}h {}m):",
+            minutes / 60,
+            minutes % 60
+        )];
+        for &resource in Resource::ALL {
+            let (before, after) = (self.before[resource], self.after[resource]);
+            if !resource.listed(before.max(after)) {
+                continue;
+            }
+            let name = resource.name();
+            match after.cmp(&before) {
+                Ordering::Greater => lines.push(format!("+{} {}", after - before, name)),
+                Ordering::Less => lines.push(format!("-{} {}", before - after, name)),
+                Ordering::Equal => {}
+            }
+        }
+        lines
+    }
+}
+
+/// Simulate the farm for the `seconds` the game was closed.
+pub fn catch_up(state: &mut State, seconds: f64) -> Summary {
+    let seconds = seconds.min(MAX_OFFLINE_SECONDS);
+    let ticks = (seconds * TICKS_PER_SECOND as f64) as u64;
+    let before = Farm::new(state);
+    let mut farm = before.clone();
+
+    // Short absences are simulated step by step, longer ones in chunks.
+    let chunk = (ticks / MAX_CHUNKS).max(1);
+    let mut remaining = ticks;
+    while remaining > 0 {
+        let k = chunk.min(remaining);
+        remaining -= k;
+        if k == 1 {
+            farm.step();
+        } else {
+            fast_forward(&mut farm, k);
+        }
+    }
+
+    let after = farm.clone();
+    farm.store(state);
+    Summary {
+        seconds,
+        before,
+        after,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{balance::Balance, resume};
+
+    /// A farm with every kind of work going on.
+    fn busy_farm() -> State {
+        let mut state = State::new(Balance::default());
+        resume(&mut state, 1_000.0);
+        for (resource, amount) in [
+            (Chickens, 500),
+            (Nests, 30),
+            (Breeding, 900),
+            (NestBuilders, 5),
+            (Corn, 2_000),
+            (CornFetchers, 2_000),
+            (Explorers, 2),
+            (Eggs, 5),
+        ] {
+            state.stock.set(resource, amount);
+        }
+        // Start the work for the new workers, like a `tick` would.
+        let mut farm = Farm::new(&state);
+        farm.start(TaskKind::BuildNests);
+        farm.start(TaskKind::FetchCorn);
+        farm.start(TaskKind::Explore);
+        farm.store(&mut state);
+        state
+    }
+
+    fn amounts(state: &State) -> Vec<(Resource, u64)> {
+        state.stock.iter().collect()
+    }
+
+    #[test]
+    fn short_absence_is_stepped() {
+        let mut caught_up = busy_farm();
+        let summary = catch_up(&mut caught_up, 100.0);
+        assert_eq!(summary.seconds, 100.0);
+
+        let mut stepped = busy_farm();
+        let mut farm = Farm::new(&stepped);
+        for _ in 0..100 * TICKS_PER_SECOND {
+            farm.step();
+        }
+        farm.store(&mut stepped);
+
+        assert_eq!(amounts(&caught_up), amounts(&stepped));
+        assert_eq!(caught_up.tasks.0, stepped.tasks.0);
+        assert_eq!(caught_up.land.revealed(), stepped.land.revealed());
+    }
+
+    #[test]
+    fn long_absence_is_chunked() {
+        let mut state = busy_farm();
+        let before = amounts(&state);
+        let summary = catch_up(&mut state, 3.0 * 24.0 * 60.0 * 60.0);
+        let after = amounts(&state);
+        // Nothing takes chickens or runaways away while the game is closed.
+        for resource in [Chickens, Runaway] {
+            let i = resource as usize;
+            assert!(after[i].1 >= before[i].1, "{:?}", resource);
+        }
+        assert!(after[Chickens as usize].1 > before[Chickens as usize].1);
+        assert!(!summary.lines().is_empty());
+    }
+
+    #[test]
+    fn absence_is_clamped() {
+        let mut state = busy_farm();
+        let summary = catch_up(&mut state, 1e12);
+        assert_eq!(summary.seconds, MAX_OFFLINE_SECONDS);
+    }
+}
@@ -0,0 +1,171 @@
+//! Finding the way across the land.
+
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use hex2d::Coordinate;
+
+use crate::terrain::{Land, Terrain};
+
+/// A way from one tile to another.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Route {
+    /// From start to goal, both included.
+    pub tiles: Vec<Coordinate>,
+    /// Sum of the `Terrain::travel_cost`s of all tiles after the start.
+    pub cost: u64,
+}
+
+/// The cheapest route over known land from `start` to the nearest of the `goals`.
+pub fn find(land: &Land, start: Coordinate, goals: &[Coordinate]) -> Option<Route> {
+    // No tile costs less than 1, so the distance never overestimates.
+    let estimate = |at: Coordinate| {
+        goals
+            .iter()
+            .map(|&goal| at.distance(goal) as u64)
+            .min()
+            .unwrap_or(0)
+    };
+    let mut open = BinaryHeap::new();
+    let mut cost = HashMap::new();
+    let mut came_from = HashMap::new();
+    cost.insert(start, 0);
+    open.push(Reverse((estimate(start), 0, (start.x, start.y))));
+
+    while let Some(Reverse((_, spent, (x, y)))) = open.pop() {
+        let at = Coordinate::new(x, y);
+        if spent > cost[&at] {
+            // Already reached more cheaply.
+            continue;
+        }
+        if goals.contains(&at) {
+            let mut tiles = vec![at];
+            while let Some(&prev) = came_from.get(tiles.last().unwrap()) {
+                tiles.push(prev);
+            }
+            tiles.reverse();
+            return Some(Route { tiles, cost: spent });
+        }
+        for next in at.neighbors().iter().copied() {
+            let step = match land.get(next).and_then(Terrain::travel_cost) {
+                Some(step) => step,
+                None => continue,
+            };
+            let total = spent + step;
+            if cost.get(&next).map_or(true, |&known| total < known) {
+                cost.insert(next, total);
+                came_from.insert(next, at);
+                open.push(Reverse((total + estimate(next), total, (next.x, next.y))));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::Terrain::*;
+
+    fn at(x: i32, y: i32) -> Coordinate {
+        Coordinate::new(x, y)
+    }
+
+    /// Meadows up to `radius` around the center, but for `exceptions`.
+    fn land(radius: i32, exceptions: &[((i32, i32), Terrain)]) -> Land {
+        let tiles = (-radius..=radius)
+            .flat_map(|x| (-radius..=radius).map(move |y| at(x, y)))
+            .filter(|tile| tile.distance(at(0, 0)) <= radius)
+            .map(|tile| {
+                let exception = exceptions.iter().find(|&&((x, y), _)| at(x, y) == tile);
+                (tile, exception.map_or(Meadow, |&(_, terrain)| terrain))
+            });
+        Land::from_tiles(tiles)
+    }
+
+    /// Checks that the route is connected and adds up to its cost.
+    fn check(land: &Land, route: &Route) {
+        for pair in route.tiles.windows(2) {
+            assert_eq!(pair[0].distance(pair[1]), 1, "{:?}", route);
+        }
+        let cost: u64 = route.tiles[1..]
+            .iter()
+            .map(|&tile| land.get(tile).and_then(Terrain::travel_cost).unwrap())
+            .sum();
+        assert_eq!(cost, route.cost, "{:?}", route);
+    }
+
+    #[test]
+    fn straight() {
+        let land = land(3, &[]);
+        let route = find(&land, at(0, 0), &[at(2, 0)]).unwrap();
+        check(&land, &route);
+        assert_eq!(route.tiles, vec![at(0, 0), at(1, 0), at(2, 0)]);
+        assert_eq!(find(&land, at(0, 0), &[at(0, 0)]).unwrap().cost, 0);
+    }
+
+    #[test]
+    fn around_water() {
+        let land = land(3, &[((1, 0), Water)]);
+        let route = find(&land, at(0, 0), &[at(2, 0)]).unwrap();
+        check(&land, &route);
+        assert!(!route.tiles.contains(&at(1, 0)));
+        assert_eq!(route.cost, 3);
+    }
+
+    #[test]
+    fn cheaper_detour() {
+        for terrain in [Rocky, Forest] {
+            let land = land(3, &[((1, 0), terrain)]);
+            let route = find(&land, at(0, 0), &[at(2, 0)]).unwrap();
+            check(&land, &route);
+            assert!(!route.tiles.contains(&at(1, 0)), "{:?}", terrain);
+            assert_eq!(route.cost, 3);
+        }
+        // Without a detour the forest is the way.
+        let strip = Land::from_tiles(vec![
+            (at(0, 0), Meadow),
+            (at(1, 0), Forest),
+            (at(2, 0), Meadow),
+        ]);
+        let route = find(&strip, at(0, 0), &[at(2, 0)]).unwrap();
+        assert_eq!(route.tiles, vec![at(0, 0), at(1, 0), at(2, 0)]);
+        assert_eq!(route.cost, 4);
+    }
+
+    #[test]
+    fn nearest_goal() {
+        let land = land(4, &[]);
+        let route = find(&land, at(0, 0), &[at(3, 0), at(-2, 0), at(0, 4)]).unwrap();
+        check(&land, &route);
+        assert_eq!(route.tiles.last(), Some(&at(-2, 0)));
+        assert_eq!(route.cost, 2);
+
+        // Nearest by travel cost, not by distance.
+        let strip = Land::from_tiles(
+            (-2..=3)
+                .map(|x| (at(x, 0), Meadow))
+                .chain([(at(-1, 0), Rocky)]),
+        );
+        let route = find(&strip, at(0, 0), &[at(3, 0), at(-2, 0)]).unwrap();
+        check(&strip, &route);
+        assert_eq!(route.tiles.last(), Some(&at(3, 0)));
+        assert_eq!(route.cost, 3);
+    }
+
+    #[test]
+    fn unreachable() {
+        let moat: Vec<_> = at(2, 0)
+            .neighbors()
+            .iter()
+            .map(|tile| ((tile.x, tile.y), Water))
+            .collect();
+        let land = land(4, &moat);
+        assert_eq!(find(&land, at(0, 0), &[at(2, 0)]), None);
+        // Outside the known land.
+        assert_eq!(find(&land, at(0, 0), &[at(9, 0)]), None);
+        assert_eq!(find(&land, at(0, 0), &[]), None);
+    }
+}
@@ -0,0 +1,146 @@
+//! Conversions between resources, declared as data. New buildings or goods only need
+//! an entry in `recipes` or `producers`.
+
+use crate::{
+    balance::Balance,
+    farm::TaskKind,
+    resources::{
+        count, Cost,
+        Resource::{self, *},
+        Stock, CAPS,
+    },
+};
+
+/// What a recipe is about, e.g. for the frontend to pick a color.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Category {
+    /// Makes something out of goods.
+    Build,
+    /// Puts roosters to work.
+    Employ,
+}
+
+/// Turns `inputs` into `outputs` when the player asks for it.
+#[derive(Clone, Debug)]
+pub struct Recipe {
+    /// Identifies the recipe.
+    pub name: &'static str,
+    pub label: String,
+    pub category: Category,
+    pub inputs: Cost,
+    pub outputs: Vec<(Resource, u64)>,
+    /// Simulation steps until the outputs arrive, 0 for right away.
+    pub duration: u64,
+    /// The recipe is offered once any of these resources exceeds its amount...
+    pub unlock: Vec<(Resource, u64)>,
+    /// ...until any of these does.
+    pub retire: Vec<(Resource, u64)>,
+    /// Work that goes on for as long as the recipe is offered.
+    pub starts: Option<TaskKind>,
+    /// Whether the map is shown once the recipe is offered.
+    pub shows_map: bool,
+}
+
+/// Every one of the `workers` turns `inputs` into `outputs` every `interval` simulation
+/// steps, as far as the inputs last.
+#[derive(Clone, Debug)]
+pub struct Producer {
+    pub workers: Resource,
+    pub inputs: Vec<(Resource, u64)>,
+    pub outputs: Vec<(Resource, u64)>,
+    pub interval: u64,
+}
+
+impl Recipe {
+    pub fn visible(&self, stock: &Stock) -> bool {
+        stock.exceeds_any(&self.unlock) && !stock.exceeds_any(&self.retire)
+    }
+
+    /// Whether there's enough of the inputs and there's room for the outputs.
+    pub fn affordable(&self, stock: &Stock) -> bool {
+        self.inputs.affordable(stock) && self.has_room(stock)
+    }
+
+    /// Whether the outputs would stay within the `CAPS` once the inputs are paid.
+    pub fn has_room(&self, stock: &Stock) -> bool {
+        let after = |r: Resource| {
+            (stock.get(r) + count(&self.outputs, r)).saturating_sub(self.inputs.of(r))
+        };
+        CAPS.iter().all(|&(a, b)| after(a) <= after(b))
+    }
+}
+
+pub fn recipes(balance: &Balance) -> Vec<Recipe> {
+    vec![
+        Recipe {
+            name: "build_nest",
+            label: "Build Nest".to_owned(),
+            category: Category::Build,
+            inputs: Cost(vec![(Eggs, balance.eggs_per_nest)]),
+            outputs: vec![(Nests, 1)],
+            duration: 0,
+            unlock: vec![
+                (Eggs, balance.eggs_per_nest.saturating_sub(1)),
+                (Chickens, 1),
+            ],
+            retire: vec![(NestBuilders, 99)],
+            starts: None,
+            shows_map: false,
+        },
+        Recipe {
+            name: "employ_nest_builder",
+            label: "Employ rooster for nest building".to_owned(),
+            category: Category::Employ,
+            inputs: Cost(vec![(Roosters, 1)]),
+            outputs: vec![(NestBuilders, 1)],
+            duration: 0,
+            unlock: vec![(NestBuilders, 1), (Nests, 100), (Chickens, 5000)],
+            retire: vec![],
+            starts: Some(TaskKind::BuildNests),
+            shows_map: true,
+        },
+        Recipe {
+            name: "send_corn_fetchers",
+            label: format!("Send {} roosters to fetch corn", balance.fetchers_per_trip),
+            category: Category::Employ,
+            inputs: Cost(vec![(Roosters, balance.fetchers_per_trip)]),
+            outputs: vec![(CornFetchers, balance.fetchers_per_trip)],
+            duration: 0,
+            unlock: vec![
+                (Corn, 1),
+                (NestBuilders, 100),
+                (CornFetchers, 0),
+                (Chickens, 10000),
+            ],
+            retire: vec![],
+            starts: Some(TaskKind::FetchCorn),
+            shows_map: false,
+        },
+        Recipe {
+            name: "send_explorer",
+            label: "Send rooster exploring".to_owned(),
+            category: Category::Employ,
+            inputs: Cost(vec![(Roosters, 1)]),
+            outputs: vec![(Explorers, 1)],
+            duration: 0,
+            unlock: vec![
+                (Explorers, 0),
+                (NestBuilders, 1),
+                (Nests, 100),
+                (Chickens, 5000),
+            ],
+            retire: vec![],
+            starts: Some(TaskKind::Explore),
+            shows_map: false,
+        },
+    ]
+}
+
+pub fn producers() -> Vec<Producer> {
+    vec![Producer {
+        workers: Nests,
+        inputs: vec![],
+        outputs: vec![(Breeding, 1)],
+        interval: 1,
+    }]
+}
@@ -0,0 +1,108 @@
+//! Upgrades that are researched once and then improve the farm for good, declared as
+//! data like the recipes.
+
+use std::{fmt, str::FromStr};
+
+use crate::resources::{Cost, Resource, Resource::*, Stock};
+
+/// Changes balance numbers once researched.
+#[derive(Clone, Debug)]
+pub struct Upgrade {
+    /// Identifies the upgrade in the save and in `requires`.
+    pub name: &'static str,
+    pub label: &'static str,
+    pub cost: Cost,
+    /// Upgrades that have to be researched first.
+    pub requires: Vec<&'static str>,
+    /// The upgrade is offered once any of these resources exceeds its amount.
+    pub unlock: Vec<(Resource, u64)>,
+    /// Balance keys and the percentage of their value they are changed to.
+    pub modifiers: Vec<(&'static str, u64)>,
+}
+
+impl Upgrade {
+    /// Whether the player gets to research this now or later.
+    pub fn visible(&self, researched: &Researched, stock: &Stock) -> bool {
+        self.researchable(researched) && stock.exceeds_any(&self.unlock)
+    }
+
+    /// Whether this isn't researched yet, but everything it `requires` is.
+    pub fn researchable(&self, researched: &Researched) -> bool {
+        !researched.contains(self.name)
+            && self.requires.iter().all(|name| researched.contains(name))
+    }
+}
+
+pub fn upgrades() -> Vec<Upgrade> {
+    vec![
+        Upgrade {
+            name: "better_nests",
+            label: "Research better nests (+50% chicks)",
+            cost: Cost(vec![(Eggs, 500)]),
+            requires: vec![],
+            unlock: vec![(Nests, 20)],
+            modifiers: vec![("chicks_per_nest", 150)],
+        },
+        Upgrade {
+            name: "warm_nests",
+            label: "Research warm nests (faster breeding)",
+            cost: Cost(vec![(Eggs, 900), (Corn, 5_000)]),
+            requires: vec!["better_nests"],
+            unlock: vec![(Chickens, 1_000)],
+            modifiers: vec![("breeding_per_batch", 75)],
+        },
+        Upgrade {
+            name: "big_baskets",
+            label: "Research big baskets (2x corn per fetch)",
+            cost: Cost(vec![(Corn, 10_000), (Roosters, 1_000)]),
+            requires: vec![],
+            unlock: vec![(CornFetchers, 0)],
+            modifiers: vec![("corn_per_fetch", 200)],
+        },
+        Upgrade {
+            name: "trail_markers",
+            label: "Research trail markers (shorter trips)",
+            cost: Cost(vec![(Corn, 50_000)]),
+            requires: vec!["big_baskets"],
+            unlock: vec![(Corn, 20_000)],
+            modifiers: vec![("travel_per_step", 50), ("forage_distance", 50)],
+        },
+        Upgrade {
+            name: "fertile_fields",
+            label: "Research fertile fields (+50% field corn)",
+            cost: Cost(vec![(Corn, 20_000), (Eggs, 500)]),
+            requires: vec!["big_baskets"],
+            unlock: vec![(Corn, 10_000)],
+            modifiers: vec![("corn_per_field", 150)],
+        },
+    ]
+}
+
+/// The names of the upgrades researched so far, saved under a single key.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Researched(pub Vec<String>);
+
+impl Researched {
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.iter().any(|researched| researched == name)
+    }
+}
+
+impl fmt::Display for Researched {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.join(","))
+    }
+}
+
+impl FromStr for Researched {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(
+            s.split(',')
+                .filter(|s| !s.is_empty())
+                .map(str::to_owned)
+                .collect(),
+        ))
+    }
+}
@@ -0,0 +1,263 @@
+//! Everything that is counted on the farm. Adding an entry to the `resources!` list
+//! below is enough to have it saved and listed to the player.
+
+use crate::{datastructures::Reader, save::Persistent};
+
+/// When a resource is listed to the player.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Shown {
+    Always,
+    NonZero,
+    Never,
+}
+
+macro_rules! resources {
+    ($(
+        $(#[$attr:meta])*
+        $variant:ident {
+            key: $key:literal,
+            name: $name:literal,
+            initial: $initial:literal,
+            shown: $shown:ident,
+        },
+    )*) => {
+        #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+        pub enum Resource {
+            $($(#[$attr])* $variant,)*
+        }
+
+        impl Resource {
+            /// All resources, in the order they are listed.
+            pub const ALL: &'static [Resource] = &[$(Resource::$variant),*];
+            pub const COUNT: usize = Self::ALL.len();
+
+            /// The key the amount is saved under.
+            pub fn key(self) -> &'static str {
+                match self {
+                    $(Resource::$variant => $key,)*
+                }
+            }
+
+            /// The `name` from the list, as it follows an amount, e.g. "+3 chickens".
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Resource::$variant => $name,)*
+                }
+            }
+
+            fn initial(self) -> u64 {
+                match self {
+                    $(Resource::$variant => $initial,)*
+                }
+            }
+
+            fn shown(self) -> Shown {
+                match self {
+                    $(Resource::$variant => Shown::$shown,)*
+                }
+            }
+        }
+    };
+}
+
+resources! {
+    Chickens {
+        key: "chickens",
+        name: "chickens",
+        initial: 1,
+        shown: Always,
+    },
+    /// Chicks that didn't survive growing up.
+    Runaway {
+        key: "runaway",
+        name: "ran away",
+        initial: 0,
+        shown: NonZero,
+    },
+    Roosters {
+        key: "roosters",
+        name: "useless roosters",
+        initial: 0,
+        shown: NonZero,
+    },
+    Chicks {
+        key: "chicks",
+        name: "chicks",
+        initial: 0,
+        shown: NonZero,
+    },
+    Eggs {
+        key: "eggs",
+        name: "eggs",
+        initial: 0,
+        shown: NonZero,
+    },
+    Corn {
+        key: "corn",
+        name: "corn",
+        initial: 0,
+        shown: NonZero,
+    },
+    NestBuilders {
+        key: "nest_builders",
+        name: "nest builders",
+        initial: 0,
+        shown: NonZero,
+    },
+    CornFetchers {
+        key: "corn_fetchers",
+        name: "corn fetchers",
+        initial: 0,
+        shown: NonZero,
+    },
+    /// Roosters revealing the land around the farm.
+    Explorers {
+        key: "explorers",
+        name: "explorers",
+        initial: 0,
+        shown: NonZero,
+    },
+    Nests {
+        key: "nests",
+        name: "nests",
+        initial: 0,
+        shown: NonZero,
+    },
+    /// Progress towards the next hatching.
+    Breeding {
+        key: "breeding",
+        name: "breeding",
+        initial: 0,
+        shown: Never,
+    },
+}
+
+/// `(a, b)`: recipes can't make more `a` than there are `b`.
+pub const CAPS: &[(Resource, Resource)] = &[
+    // Every nest needs a chicken sitting on it.
+    (Resource::Nests, Resource::Chickens),
+];
+
+impl Resource {
+    /// Whether to list the resource when there's `amount` of it.
+    pub fn listed(self, amount: u64) -> bool {
+        match self.shown() {
+            Shown::Always => true,
+            Shown::NonZero => amount > 0,
+            Shown::Never => false,
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|r| r.key() == key)
+    }
+}
+
+/// How much of `resource` the list contains.
+pub fn count(list: &[(Resource, u64)], resource: Resource) -> u64 {
+    list.iter()
+        .filter(|&&(r, _)| r == resource)
+        .map(|&(_, n)| n)
+        .sum()
+}
+
+/// Resources that are paid together: all of them or none. Checking whether a cost is
+/// affordable and paying it go through the same numbers, so they can't disagree.
+#[derive(Clone, Default, Debug)]
+pub struct Cost(pub Vec<(Resource, u64)>);
+
+impl Cost {
+    /// How much of `resource` it takes.
+    pub fn of(&self, resource: Resource) -> u64 {
+        count(&self.0, resource)
+    }
+
+    pub fn affordable(&self, stock: &Stock) -> bool {
+        self.0.iter().all(|&(r, _)| stock.get(r) >= self.of(r))
+    }
+}
+
+/// The amount of every resource, each saved under its own key and observable through
+/// a `Reader`.
+pub struct Stock {
+    amounts: Vec<Persistent<u64>>,
+}
+
+impl Default for Stock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stock {
+    pub fn load() -> Self {
+        Self::open(true)
+    }
+
+    /// What a new farm starts with, never saved.
+    pub fn new() -> Self {
+        Self::open(false)
+    }
+
+    fn open(saved: bool) -> Self {
+        Self {
+            amounts: Resource::ALL
+                .iter()
+                .map(|r| Persistent::open(r.initial(), saved.then(|| r.key())))
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, resource: Resource) -> u64 {
+        self.amounts[resource as usize].get()
+    }
+
+    /// Notified whenever the amount of `resource` changes.
+    pub fn reader(&self, resource: Resource) -> Reader<u64> {
+        self.amounts[resource as usize].make_reader()
+    }
+
+    /// Only writes to storage (and notifies readers) if the amount actually changes.
+    pub fn set(&mut self, resource: Resource, amount: u64) {
+        let persistent = &self.amounts[resource as usize];
+        if *persistent != amount {
+            persistent.set(amount);
+        }
+    }
+
+    /// Back to what a new farm starts with.
+    pub fn reset(&mut self) {
+        for &resource in Resource::ALL {
+            self.set(resource, resource.initial());
+        }
+    }
+
+    /// Stops at `u64::MAX` instead of overflowing.
+    pub fn add(&mut self, resource: Resource, amount: u64) {
+        self.set(resource, self.get(resource).saturating_add(amount));
+    }
+
+    /// Pay `cost` if there's enough of everything. Returns whether it was paid, nothing
+    /// is written otherwise.
+    pub fn try_spend(&mut self, cost: &Cost) -> bool {
+        if !cost.affordable(self) {
+            return false;
+        }
+        for &(r, n) in &cost.0 {
+            // Can't fail, `affordable` added up every entry.
+            if n > 0 {
+                self.amounts[r as usize].try_spend(n);
+            }
+        }
+        true
+    }
+
+    /// Whether any of the resources exceeds its amount.
+    pub fn exceeds_any(&self, thresholds: &[(Resource, u64)]) -> bool {
+        thresholds.iter().any(|&(r, n)| self.get(r) > n)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Resource, u64)> + '_ {
+        Resource::ALL.iter().map(move |&r| (r, self.get(r)))
+    }
+}
@@ -0,0 +1,338 @@
+use std::{
+    cmp::Ordering,
+    fmt::{Debug, Display},
+    ops::{AddAssign, Deref, DerefMut, MulAssign, RemAssign, SubAssign},
+    str::FromStr,
+};
+
+use hex2d::Coordinate;
+
+use crate::{
+    datastructures::{Reader, Sensor},
+    BigCount,
+};
+
+mod storage;
+pub use storage::{data_dir, init, read_file, transaction_loop, transaction_step, Options};
+#[cfg(test)]
+pub(crate) use storage::{init_temp, transaction};
+
+pub(crate) fn save(key: impl ToString, value: impl ToString) {
+    storage::set(&key.to_string(), &value.to_string())
+}
+
+pub(crate) fn load<T: FromStr>(key: impl ToString) -> Option<T>
+where
+    T::Err: Debug,
+{
+    try_load(key).map(Result::unwrap)
+}
+
+fn try_load<T: FromStr>(key: impl ToString) -> Option<Result<T, T::Err>> {
+    storage::get(&key.to_string()).map(|s| s.parse())
+}
+
+pub trait Save {
+    fn save(&self, key: impl Display);
+    fn load(&mut self, key: impl Display);
+}
+
+pub struct Saveable<T> {
+    value: T,
+    /// `None` if the value is never saved.
+    key: Option<String>,
+}
+
+pub type ComplexSaveable<T> = Saveable<ComplexSave<T>>;
+
+impl<T: Save> Saveable<T> {
+    pub fn new(value: impl Into<T>, key: impl ToString) -> Self {
+        Self::open(value, Some(key))
+    }
+
+    /// Saved under `key` if there is one, without ever touching storage otherwise.
+    pub fn open(value: impl Into<T>, key: Option<impl ToString>) -> Self {
+        let mut this = Self {
+            value: value.into(),
+            key: key.map(|key| key.to_string()),
+        };
+        this.load();
+        this
+    }
+
+    pub fn default(key: impl ToString) -> Self
+    where
+        T: Default,
+    {
+        Self::new(T::default(), key)
+    }
+
+    fn save(&self) {
+        if let Some(key) = &self.key {
+            self.value.save(key)
+        }
+    }
+
+    fn load(&mut self) {
+        if let Some(key) = &self.key {
+            self.value.load(key)
+        }
+    }
+
+    pub fn update(&mut self, f: impl FnOnce(&mut T)) {
+        f(&mut self.value);
+        self.save()
+    }
+    pub fn set(&mut self, t: impl Into<T>) {
+        self.update(|v| *v = t.into());
+    }
+}
+
+/// Numbers that know when subtracting would go below zero.
+pub trait CheckedSub: Sized {
+    fn checked_sub(&self, rhs: &Self) -> Option<Self>;
+}
+
+macro_rules! checked_sub {
+    ($($t:ty),*) => {$(
+        impl CheckedSub for $t {
+            fn checked_sub(&self, rhs: &Self) -> Option<Self> {
+                <$t>::checked_sub(*self, *rhs)
+            }
+        }
+    )*};
+}
+
+checked_sub!(u8, u16, u32, u64, u128, usize);
+
+impl CheckedSub for BigCount {
+    fn checked_sub(&self, rhs: &Self) -> Option<Self> {
+        BigCount::checked_sub(self, rhs)
+    }
+}
+
+impl<T> Deref for Saveable<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<T: Save + AddAssign<R>, R> AddAssign<R> for Saveable<T> {
+    fn add_assign(&mut self, rhs: R) {
+        self.update(|val| *val += rhs)
+    }
+}
+
+impl<T: Save + MulAssign<R>, R> MulAssign<R> for Saveable<T> {
+    fn mul_assign(&mut self, rhs: R) {
+        self.update(|val| *val *= rhs)
+    }
+}
+
+impl<T: Save + SubAssign<R>, R> SubAssign<R> for Saveable<T> {
+    fn sub_assign(&mut self, rhs: R) {
+        self.update(|val| *val -= rhs)
+    }
+}
+
+impl<T: Save + RemAssign<R>, R> RemAssign<R> for Saveable<T> {
+    fn rem_assign(&mut self, rhs: R) {
+        self.update(|val| *val %= rhs)
+    }
+}
+
+impl<T: PartialEq<R>, R> PartialEq<R> for Saveable<T> {
+    fn eq(&self, other: &R) -> bool {
+        self.value.eq(other)
+    }
+}
+
+impl<T: PartialOrd<R>, R> PartialOrd<R> for Saveable<T> {
+    fn partial_cmp(&self, other: &R) -> Option<Ordering> {
+        self.value.partial_cmp(other)
+    }
+}
+
+impl<T: FromStr> Save for T
+where
+    for<'a> &'a T: ToString,
+    T::Err: Debug,
+{
+    fn save(&self, key: impl ToString) {
+        save(key, self)
+    }
+
+    fn load(&mut self, key: impl ToString) {
+        if let Some(val) = load(key) {
+            *self = val;
+        }
+    }
+}
+
+/// A sensor that writes through to storage on every change, so its readers and the
+/// save never disagree.
+pub struct Persistent<T> {
+    sensor: Sensor<T>,
+    /// `None` if the value is never saved.
+    key: Option<String>,
+}
+
+impl<T: Save + Clone> Persistent<T> {
+    pub fn new(value: T, key: impl ToString) -> Self {
+        Self::open(value, Some(key))
+    }
+
+    /// Saved under `key` if there is one, without ever touching storage otherwise.
+    pub fn open(mut value: T, key: Option<impl ToString>) -> Self {
+        let key = key.map(|key| key.to_string());
+        if let Some(key) = &key {
+            value.load(key);
+        }
+        Self {
+            sensor: Sensor::raw(value),
+            key,
+        }
+    }
+
+    pub fn default(key: impl ToString) -> Self
+    where
+        T: Default,
+    {
+        Self::new(Default::default(), key)
+    }
+
+    pub fn get(&self) -> T {
+        self.sensor.get()
+    }
+    pub fn make_reader(&self) -> Reader<T> {
+        self.sensor.make_reader()
+    }
+    pub fn set(&self, val: T) {
+        if let Some(key) = &self.key {
+            val.save(key);
+        }
+        self.sensor.set(val);
+    }
+    pub fn update(&self, f: impl FnOnce(&mut T)) {
+        let mut val = self.get();
+        f(&mut val);
+        self.set(val);
+    }
+    pub fn modify(&self, f: impl FnOnce(T) -> T) -> T {
+        let new = f(self.get());
+        self.set(new.clone());
+        new
+    }
+}
+
+impl<T: Save + Clone + CheckedSub> Persistent<T> {
+    /// The value minus `rhs`, unless there isn't enough. Doesn't write anything.
+    pub fn checked_sub(&self, rhs: impl Into<T>) -> Option<T> {
+        self.sensor.with(|val| val.checked_sub(&rhs.into()))
+    }
+
+    /// Subtract `rhs` if there's enough. Returns whether it was subtracted, nothing is
+    /// written otherwise.
+    pub fn try_spend(&self, rhs: impl Into<T>) -> bool {
+        match self.checked_sub(rhs) {
+            Some(val) => {
+                self.set(val);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<T: Save + Clone + AddAssign> AddAssign<T> for Persistent<T> {
+    fn add_assign(&mut self, rhs: T) {
+        self.update(|val| *val += rhs)
+    }
+}
+
+impl<T: Save + Clone + MulAssign> MulAssign<T> for Persistent<T> {
+    fn mul_assign(&mut self, rhs: T) {
+        self.update(|val| *val *= rhs)
+    }
+}
+
+impl<T: Save + Clone + SubAssign> SubAssign<T> for Persistent<T> {
+    fn sub_assign(&mut self, rhs: T) {
+        self.update(|val| *val -= rhs)
+    }
+}
+
+impl<T: Save + Clone + RemAssign> RemAssign<T> for Persistent<T> {
+    fn rem_assign(&mut self, rhs: T) {
+        self.update(|val| *val %= rhs)
+    }
+}
+
+impl<T: PartialEq> PartialEq<T> for Persistent<T> {
+    fn eq(&self, other: &T) -> bool {
+        self.sensor.with(|val| val.eq(other))
+    }
+}
+
+impl<T: PartialOrd> PartialOrd<T> for Persistent<T> {
+    fn partial_cmp(&self, other: &T) -> Option<Ordering> {
+        self.sensor.with(|val| val.partial_cmp(other))
+    }
+}
+
+pub struct ComplexSave<T>(T);
+
+impl<T> From<T> for ComplexSave<T> {
+    fn from(t: T) -> Self {
+        Self(t)
+    }
+}
+
+impl<T> Deref for ComplexSave<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+impl<T> DerefMut for ComplexSave<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl Save for ComplexSave<Coordinate> {
+    fn save(&self, key: impl Display) {
+        self.x.save(format_args!("{}/x", key));
+        self.y.save(format_args!("{}/y", key));
+    }
+
+    fn load(&mut self, key: impl Display) {
+        self.x.load(format_args!("{}/x", key));
+        self.y.load(format_args!("{}/y", key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_spending_writes_nothing() {
+        let _root = init_temp("persistent-spend");
+        let eggs = Persistent::new(5_u64, "eggs");
+        let reader = eggs.make_reader();
+        transaction(|| assert!(!eggs.try_spend(6_u64)));
+        assert_eq!(eggs.get(), 5);
+        assert!(!reader.changed());
+        assert_eq!(load("eggs"), None::<u64>);
+        assert_eq!(eggs.checked_sub(5_u64), Some(0));
+
+        transaction(|| assert!(eggs.try_spend(2_u64)));
+        assert_eq!(eggs.get(), 3);
+        assert!(reader.changed());
+        assert_eq!(load("eggs"), Some(3_u64));
+    }
+}
@@ -0,0 +1,328 @@
+#[cfg(all(test, not(target_arch = "wasm32")))]
+use std::cell::RefCell;
+#[cfg(all(not(test), not(target_arch = "wasm32")))]
+use std::sync::Once;
+use std::{
+    future::Future,
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+pub fn set(key: &str, value: &str) {
+    assert!(flags(|f| f.transaction.load(Ordering::Relaxed)));
+    let odd = flags(|f| f.odd.load(Ordering::Relaxed));
+    set_inner(&format!("{}/{}", odd as u8, key), value)
+}
+
+fn set_inner(key: &str, value: &str) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        quad_storage_sys::set(key, value)
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        if with_options(|o| o.read_only) {
+            return;
+        }
+        let path = path(key);
+        if let Some(parent) = path.parent() {
+            if !parent.exists() {
+                std::fs::create_dir_all(parent).unwrap();
+            }
+        }
+        std::fs::write(path, value).unwrap();
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn path(key: &str) -> PathBuf {
+    let mut path = with_options(|o| o.root.clone());
+
+    for elem in key.split('/') {
+        path.push(elem);
+    }
+    path
+}
+
+/// Where and whether the game is saved on native platforms. Ignored on the web.
+pub struct Options {
+    /// Directory containing the `odd` marker and both transaction frames.
+    pub root: PathBuf,
+    /// Start from a fresh save.
+    pub reset: bool,
+    /// Never write anything to disk.
+    pub read_only: bool,
+}
+
+/// The platform's local data directory, `None` if there is none (e.g. no home
+/// directory) or on the web.
+pub fn data_dir() -> Option<PathBuf> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        directories::ProjectDirs::from("", "", "tofuwabohu")
+            .map(|dirs| dirs.data_local_dir().to_owned())
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        None
+    }
+}
+
+impl Default for Options {
+    /// The platform's local data directory, or the current directory if there is none.
+    fn default() -> Self {
+        Self {
+            root: data_dir().unwrap_or_default(),
+            reset: false,
+            read_only: false,
+        }
+    }
+}
+
+#[cfg(all(not(test), not(target_arch = "wasm32")))]
+static OPTIONS_INIT: Once = Once::new();
+/// Only written once, inside `OPTIONS_INIT`, by `init` or the first storage access
+/// without it.
+#[cfg(all(not(test), not(target_arch = "wasm32")))]
+static mut OPTIONS: Option<Options> = None;
+
+#[cfg(all(not(test), not(target_arch = "wasm32")))]
+fn set_options(options: Options) {
+    let mut options = Some(options);
+    // SAFETY: `OPTIONS_INIT` makes this the only write, and nothing reads before it.
+    OPTIONS_INIT.call_once(|| unsafe { OPTIONS = options.take() });
+    assert!(options.is_none(), "storage initialized after its first use");
+}
+
+#[cfg(all(not(test), not(target_arch = "wasm32")))]
+fn with_options<R>(f: impl FnOnce(&Options) -> R) -> R {
+    // SAFETY: see `set_options`, after `call_once` returns `OPTIONS` never changes.
+    OPTIONS_INIT.call_once(|| unsafe { OPTIONS = Some(Options::default()) });
+    f(unsafe { OPTIONS.as_ref() }.unwrap())
+}
+
+// Every test thread gets its own save directory.
+#[cfg(all(test, not(target_arch = "wasm32")))]
+thread_local! {
+    static OPTIONS: RefCell<Option<Options>> = RefCell::new(None);
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+fn set_options(options: Options) {
+    OPTIONS.with(|o| *o.borrow_mut() = Some(options));
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+fn with_options<R>(f: impl FnOnce(&Options) -> R) -> R {
+    OPTIONS.with(|o| f(o.borrow_mut().get_or_insert_with(Options::default)))
+}
+
+/// Apply `options`, including `reset`. Must be called before anything gets loaded.
+pub fn init(options: Options) {
+    #[cfg(target_arch = "wasm32")]
+    let _ = options;
+    #[cfg(not(target_arch = "wasm32"))]
+    set_options(options);
+    #[cfg(not(target_arch = "wasm32"))]
+    with_options(|o| {
+        if o.reset && !o.read_only {
+            // Only what the storage wrote, the root may hold other profiles, a
+            // `balance.toml` or things that have nothing to do with the game.
+            let odd = o.root.join("odd");
+            match std::fs::remove_file(&odd) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                    panic!("could not reset {}: {}", odd.display(), err)
+                }
+                _ => {}
+            }
+            for frame in ["0", "1"] {
+                let frame = o.root.join(frame);
+                match std::fs::remove_dir_all(&frame) {
+                    Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                        panic!("could not reset {}: {}", frame.display(), err)
+                    }
+                    _ => {}
+                }
+            }
+        }
+    });
+}
+
+/// Read a file from the save directory that isn't part of the save itself, like a
+/// config file. There are no such files on the web.
+pub fn read_file(name: &str) -> Option<String> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = name;
+        None
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::fs::read_to_string(with_options(|o| o.root.join(name))).ok()
+    }
+}
+
+pub fn get(key: &str) -> Option<String> {
+    // Only do it while in the "loading" stage, not during the game itself,
+    // as you may get inconsistent state.
+    assert!(!flags(|f| f.transaction.load(Ordering::Relaxed)));
+    // Always read from the last successful frame.
+    // If there was no previous successful frame, immediately bail out, there can't
+    // be any actual values anyway.
+    let odd: bool = get_inner("odd")?.parse().unwrap();
+    get_inner(&format!("{}/{}", odd as u8, key))
+}
+
+fn get_inner(key: &str) -> Option<String> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        quad_storage_sys::get(key)
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        // A read-only reset can't delete anything, so just pretend there's nothing.
+        if with_options(|o| o.reset && o.read_only) {
+            return None;
+        }
+        let path = path(key);
+        std::fs::read_to_string(path).ok()
+    }
+}
+
+struct Flags {
+    /// Which frame the current transaction writes to.
+    odd: AtomicBool,
+    transaction: AtomicBool,
+}
+
+impl Flags {
+    const fn new() -> Self {
+        Self {
+            odd: AtomicBool::new(false),
+            transaction: AtomicBool::new(false),
+        }
+    }
+}
+
+#[cfg(not(test))]
+fn flags<R>(f: impl FnOnce(&Flags) -> R) -> R {
+    static FLAGS: Flags = Flags::new();
+    f(&FLAGS)
+}
+
+/// Tests run in parallel, each on its own storage.
+#[cfg(test)]
+fn flags<R>(f: impl FnOnce(&Flags) -> R) -> R {
+    thread_local! {
+        static FLAGS: Flags = Flags::new();
+    }
+    FLAGS.with(f)
+}
+
+struct Transactor {
+    odd: bool,
+}
+
+impl Drop for Transactor {
+    fn drop(&mut self) {
+        assert!(flags(|f| f.transaction.swap(false, Ordering::Relaxed)));
+    }
+}
+
+impl Transactor {
+    fn new() -> Self {
+        assert!(!flags(|f| f.transaction.swap(true, Ordering::Relaxed)));
+        // Figure out the last successfull transaction.
+        Self {
+            odd: get_inner("odd").map(|s| s.parse().unwrap()).unwrap_or(true),
+        }
+    }
+    async fn step<F: Future<Output = ()>>(&mut self, mut f: impl FnMut() -> F) {
+        self.begin();
+        // Perform transaction
+        f().await;
+        self.commit();
+    }
+
+    fn begin(&mut self) {
+        // Use the next frame.
+        self.odd = !self.odd;
+
+        // Preserve previous state.
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            use quad_storage_sys::*;
+            let oddstr = format!("{}/", (!odd) as u8);
+            if len() > 100000 {
+                // hotfix for bugs that accidentally produce infinite entries
+                clear();
+            }
+            for i in 0..len() {
+                let key = key(i).unwrap();
+                if key.starts_with(&oddstr) {
+                    let new_key = format!("{}/{}", odd as u8, &key[2..]);
+                    let val = get(&key).unwrap();
+                    set(&new_key, &val);
+                }
+            }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if !with_options(|o| o.read_only) {
+            let dest = path(&(self.odd as u8).to_string());
+            let _ = std::fs::remove_dir_all(&dest);
+            let _ = copy_dir::copy_dir(path(&((!self.odd) as u8).to_string()), dest);
+        }
+        // Let all the regular storage ops know what prefix to use.
+        flags(|f| f.odd.store(self.odd, Ordering::Relaxed));
+    }
+
+    fn commit(&self) {
+        // Transaction successfully done
+        set_inner("odd", &self.odd.to_string());
+    }
+}
+
+pub async fn transaction_loop<F: Future<Output = ()>>(mut f: impl FnMut() -> F) {
+    let mut trans = Transactor::new();
+    loop {
+        trans.step(&mut f).await;
+    }
+}
+
+pub async fn transaction_step<F: Future<Output = ()>>(f: impl FnMut() -> F) {
+    Transactor::new().step(f).await
+}
+
+/// Run `f` as a single transaction, without an executor.
+#[cfg(test)]
+pub(crate) fn transaction(f: impl FnOnce()) {
+    let mut trans = Transactor::new();
+    trans.begin();
+    f();
+    trans.commit();
+}
+
+/// Start each test thread from its own empty save directory, which is removed
+/// again when the returned guard is dropped.
+#[cfg(test)]
+#[must_use]
+pub(crate) fn init_temp(name: &str) -> TempRoot {
+    let root = std::env::temp_dir().join(format!("tofuwabohu-{}-{}", name, std::process::id()));
+    init(Options {
+        root: root.clone(),
+        reset: true,
+        read_only: false,
+    });
+    TempRoot(root)
+}
+
+#[cfg(test)]
+pub(crate) struct TempRoot(PathBuf);
+
+#[cfg(test)]
+impl Drop for TempRoot {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
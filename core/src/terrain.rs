@@ -0,0 +1,318 @@
+//! The land the farm is built on, generated from a seed so only the seed and what has
+//! been seen of it need saving.
+
+use std::{fmt, str::FromStr};
+
+use hex2d::Coordinate;
+
+use crate::{datastructures::SparseMap, rng::mix, save::Saveable};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Terrain {
+    Meadow,
+    Forest,
+    Water,
+    /// Wild corn, fields built here grow twice as much.
+    CornField,
+    Rocky,
+}
+
+impl Terrain {
+    pub const ALL: [Terrain; 5] = [
+        Terrain::Meadow,
+        Terrain::Forest,
+        Terrain::Water,
+        Terrain::CornField,
+        Terrain::Rocky,
+    ];
+
+    /// How a tile's ground reads in a sentence, e.g. "rocky ground".
+    pub fn name(self) -> &'static str {
+        match self {
+            Terrain::Meadow => "meadow",
+            Terrain::Forest => "forest",
+            Terrain::Water => "water",
+            Terrain::CornField => "corn field",
+            Terrain::Rocky => "rocky ground",
+        }
+    }
+
+    fn key(self) -> &'static str {
+        match self {
+            Terrain::Meadow => "meadow",
+            Terrain::Forest => "forest",
+            Terrain::Water => "water",
+            Terrain::CornField => "corn_field",
+            Terrain::Rocky => "rocky",
+        }
+    }
+
+    /// Whether anything can be built here.
+    pub fn buildable(self) -> bool {
+        matches!(self, Terrain::Meadow | Terrain::CornField)
+    }
+
+    /// Effort to walk onto a tile, `None` if it can't be walked onto.
+    pub fn travel_cost(self) -> Option<u64> {
+        match self {
+            Terrain::Meadow | Terrain::CornField => Some(1),
+            Terrain::Forest => Some(3),
+            Terrain::Rocky => Some(5),
+            Terrain::Water => None,
+        }
+    }
+}
+
+impl fmt::Display for Terrain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.key())
+    }
+}
+
+impl FromStr for Terrain {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|t| t.key() == s)
+            .ok_or_else(|| format!("unknown terrain `{}`", s))
+    }
+}
+
+fn hash(seed: u64, x: i32, y: i32, salt: u64) -> u64 {
+    mix(seed ^ mix(x as u64 ^ mix(y as u64 ^ mix(salt))))
+}
+
+/// Smooth noise in `0..1024` with features about `scale` tiles wide. Random values on a
+/// coarse grid, interpolated in between.
+fn noise(seed: u64, at: Coordinate, scale: i32, salt: u64) -> i64 {
+    let (cx, fx) = (at.x.div_euclid(scale), at.x.rem_euclid(scale) as i64);
+    let (cy, fy) = (at.y.div_euclid(scale), at.y.rem_euclid(scale) as i64);
+    let s = scale as i64;
+    let corner = |dx, dy| (hash(seed, cx + dx, cy + dy, salt) % 1024) as i64;
+    let top = corner(0, 0) * (s - fx) + corner(1, 0) * fx;
+    let bottom = corner(0, 1) * (s - fx) + corner(1, 1) * fx;
+    (top * (s - fy) + bottom * fy) / (s * s)
+}
+
+/// The terrain at `at` of the land grown from `seed`. Always the same for the same
+/// arguments.
+pub fn generate(seed: u64, at: Coordinate) -> Terrain {
+    // The farmhouse needs some room.
+    if at.distance(Coordinate::new(0, 0)) <= 1 {
+        return Terrain::Meadow;
+    }
+    let elevation = (noise(seed, at, 7, 1) * 3 + noise(seed, at, 3, 2)) / 4;
+    let moisture = (noise(seed, at, 5, 3) * 3 + noise(seed, at, 2, 4)) / 4;
+    if elevation < 360 {
+        Terrain::Water
+    } else if elevation > 680 {
+        Terrain::Rocky
+    } else if moisture > 590 {
+        Terrain::Forest
+    } else if moisture < 400 {
+        Terrain::CornField
+    } else {
+        Terrain::Meadow
+    }
+}
+
+/// How much of a tile is known.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Visibility {
+    /// Seen from a neighboring tile. Can be scouted, but not built on.
+    Glimpsed,
+    Revealed,
+}
+
+impl fmt::Display for Visibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Visibility::Glimpsed => write!(f, "g"),
+            Visibility::Revealed => write!(f, "r"),
+        }
+    }
+}
+
+impl FromStr for Visibility {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "g" => Ok(Visibility::Glimpsed),
+            "r" => Ok(Visibility::Revealed),
+            _ => Err(format!("unknown visibility `{}`", s)),
+        }
+    }
+}
+
+/// The known part of the land. Only the seed and what has been seen are saved, the
+/// terrain is generated again on every start.
+pub struct Land {
+    /// 0 until the first start picked one.
+    seed: Saveable<u64>,
+    fog: Saveable<SparseMap<Visibility>>,
+    tiles: SparseMap<Terrain>,
+}
+
+impl Default for Land {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Land {
+    pub fn load() -> Self {
+        Self::open(true)
+    }
+
+    /// Nothing seen yet, never saved.
+    pub fn new() -> Self {
+        Self::open(false)
+    }
+
+    fn open(saved: bool) -> Self {
+        Self {
+            seed: Saveable::open(0_u64, saved.then(|| "seed")),
+            fog: Saveable::open(SparseMap::default(), saved.then(|| "fog")),
+            tiles: SparseMap::default(),
+        }
+    }
+
+    /// Known land made of `tiles` instead of grown from a seed.
+    #[cfg(test)]
+    pub(crate) fn from_tiles(tiles: impl IntoIterator<Item = (Coordinate, Terrain)>) -> Self {
+        let mut land = Self::new();
+        for (at, terrain) in tiles {
+            land.fog.update(|fog| {
+                fog.insert(at, Visibility::Revealed);
+            });
+            land.tiles.insert(at, terrain);
+        }
+        land
+    }
+
+    pub fn seed(&self) -> u64 {
+        *self.seed
+    }
+
+    /// Pick a seed from `entropy`, unless the save already has one, and generate the
+    /// terrain of everything that has been seen.
+    pub fn sow(&mut self, entropy: u64) {
+        if *self.seed == 0 {
+            self.seed.set(mix(entropy).max(1));
+        }
+        let seed = *self.seed;
+        for (at, _) in self.fog.iter() {
+            self.tiles.insert(at, generate(seed, at));
+        }
+    }
+
+    /// How many tiles have been revealed.
+    pub fn revealed(&self) -> usize {
+        self.fog
+            .iter()
+            .filter(|&(_, &visibility)| visibility == Visibility::Revealed)
+            .count()
+    }
+
+    /// Whether nothing has been seen yet.
+    pub fn is_unexplored(&self) -> bool {
+        self.fog.is_empty()
+    }
+
+    /// Reveal `at` and glimpse its neighbors.
+    pub fn reveal(&mut self, at: Coordinate) {
+        let seed = *self.seed;
+        let tiles = &mut self.tiles;
+        self.fog.update(|fog| {
+            fog.insert(at, Visibility::Revealed);
+            tiles.insert(at, generate(seed, at));
+            for n in at.neighbors().iter().copied() {
+                if !fog.contains(n) {
+                    fog.insert(n, Visibility::Glimpsed);
+                    tiles.insert(n, generate(seed, n));
+                }
+            }
+        });
+    }
+
+    /// The glimpsed tile explorers reveal next: one of those closest to the center.
+    pub fn frontier(&self) -> Option<Coordinate> {
+        let seed = *self.seed;
+        self.fog
+            .iter()
+            .filter(|&(_, &visibility)| visibility == Visibility::Glimpsed)
+            .map(|(at, _)| at)
+            .min_by_key(|&at| {
+                let distance = at.distance(Coordinate::new(0, 0));
+                (distance, hash(seed, at.x, at.y, 5))
+            })
+    }
+
+    /// `None` if the tile hasn't been seen yet.
+    pub fn get(&self, at: Coordinate) -> Option<Terrain> {
+        self.tiles.get(at).copied()
+    }
+
+    pub fn visibility(&self, at: Coordinate) -> Option<Visibility> {
+        self.fog.get(at).copied()
+    }
+
+    /// Whether `at` has been revealed and can be built on.
+    pub fn buildable(&self, at: Coordinate) -> bool {
+        self.visibility(at) == Some(Visibility::Revealed)
+            && self.get(at).map_or(false, Terrain::buildable)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Coordinate, Terrain)> + '_ {
+        self.tiles.iter().map(|(at, &terrain)| (at, terrain))
+    }
+
+    /// How far from the center the seen land reaches.
+    pub fn radius(&self) -> i32 {
+        self.iter()
+            .map(|(at, _)| at.distance(Coordinate::new(0, 0)))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Terrain::*, *};
+
+    /// Saves only keep the seed, so changing what a seed grows into changes the land
+    /// under existing farms.
+    #[test]
+    fn generation_is_stable() {
+        let pinned = [
+            (1, (0, 0), Meadow),
+            (1, (1, -1), Meadow),
+            (1, (0, 1), Meadow),
+            (1, (2, 0), Meadow),
+            (1, (-4, 2), CornField),
+            (1, (7, 7), Water),
+            (1, (5, 5), Forest),
+            (1, (-7, 0), Rocky),
+            (1, (9, -2), Rocky),
+            (1, (-25, 30), CornField),
+            (1, (-1, -60), Water),
+            (1, (100, -100), Forest),
+            (0x5eed_f00d, (0, 0), Meadow),
+            (0x5eed_f00d, (2, 0), Water),
+            (0x5eed_f00d, (3, -5), Rocky),
+            (0x5eed_f00d, (-4, 2), Forest),
+            (0x5eed_f00d, (-10, -3), Meadow),
+            (0x5eed_f00d, (-7, 0), CornField),
+            (0x5eed_f00d, (-1, -60), Rocky),
+            (0x5eed_f00d, (100, -100), CornField),
+        ];
+        for (seed, (x, y), terrain) in pinned {
+            let at = Coordinate::new(x, y);
+            assert_eq!(generate(seed, at), terrain, "seed {} at {:?}", seed, at);
+        }
+    }
+}
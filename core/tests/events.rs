@@ -0,0 +1,108 @@
+//! Events follow from the seed, and responding to them costs or loses what it says.
+
+use tofuwabohu_core::{
+    apply,
+    balance::Balance,
+    event,
+    events::{Event, EventKind},
+    resources::Resource::{self, *},
+    resume, tick, Action, State,
+};
+
+/// A farm with plenty of everything events are about, and events coming every now and
+/// then. `now` decides the seed.
+fn eventful_farm(now: f64, event_chance: u64) -> State {
+    let mut state = State::new(Balance {
+        event_chance,
+        ..Balance::default()
+    });
+    resume(&mut state, now);
+    for (resource, amount) in [
+        (Chickens, 1_000),
+        (Nests, 500),
+        (Runaway, 1_000),
+        (Roosters, 100_000),
+        (Corn, 100_000),
+    ] {
+        state.stock.set(resource, amount);
+    }
+    state
+}
+
+fn amounts(state: &State) -> Vec<(Resource, u64)> {
+    state.stock.iter().collect()
+}
+
+/// Play for a while, ignoring every other event.
+fn play(state: &mut State) -> Vec<Event> {
+    let mut seen = vec![];
+    for i in 0..200 {
+        tick(state, 1.0);
+        if let Some(waiting) = event(state) {
+            seen.push(waiting);
+            if i % 2 == 0 {
+                assert!(apply(state, Action::Respond(false)));
+            }
+        }
+    }
+    seen
+}
+
+#[test]
+fn same_seed_same_events() {
+    let mut a = eventful_farm(1_000.0, 600);
+    let mut b = eventful_farm(1_000.0, 600);
+    let events = play(&mut a);
+    assert!(events.len() > 3, "{:?}", events);
+    assert_eq!(events, play(&mut b));
+    assert_eq!(amounts(&a), amounts(&b));
+
+    let mut c = eventful_farm(2_000.0, 600);
+    assert_ne!(events, play(&mut c));
+}
+
+#[test]
+fn responses() {
+    let mut seen = vec![];
+    for i in 0..100 {
+        // Two identical farms, one responds and one doesn't.
+        let now = 1_000.0 + i as f64;
+        let mut paid = eventful_farm(now, 1);
+        let mut ignored = eventful_farm(now, 1);
+        tick(&mut paid, 1.0);
+        tick(&mut ignored, 1.0);
+        let waiting = event(&paid).unwrap();
+        assert_eq!(event(&ignored), Some(waiting));
+        let before = amounts(&paid);
+        let get = |amounts: &[(Resource, u64)], resource: Resource| amounts[resource as usize].1;
+
+        assert!(apply(&mut paid, Action::Respond(true)));
+        let after = amounts(&paid);
+        for &(resource, n) in &waiting.cost(&paid.balance).0 {
+            assert_eq!(get(&after, resource), get(&before, resource) - n);
+        }
+        if waiting.kind == EventKind::FlockReturns {
+            assert_eq!(get(&after, Runaway), get(&before, Runaway) - waiting.amount);
+        }
+
+        assert!(apply(&mut ignored, Action::Respond(false)));
+        let after = amounts(&ignored);
+        let lost = match waiting.kind {
+            EventKind::FoxRaid => Chickens,
+            EventKind::Storm => Nests,
+            EventKind::FlockReturns => Runaway,
+        };
+        let loss = match waiting.kind {
+            EventKind::FlockReturns => 0,
+            _ => waiting.amount,
+        };
+        assert_eq!(get(&after, lost), get(&before, lost) - loss);
+
+        assert_eq!(event(&paid), None);
+        assert_eq!(event(&ignored), None);
+        if !seen.contains(&waiting.kind) {
+            seen.push(waiting.kind);
+        }
+    }
+    assert_eq!(seen.len(), EventKind::ALL.len());
+}
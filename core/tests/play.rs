@@ -0,0 +1,167 @@
+//! Plays a farm through the public API. It's never saved, so no storage or
+//! transactions are involved.
+
+use tofuwabohu_core::{
+    actions, apply,
+    balance::Balance,
+    event, prestige_reward,
+    resources::{Cost, Resource::*},
+    resume, tick, Action, State,
+};
+
+fn new_farm() -> State {
+    let mut state = State::new(Balance::default());
+    assert!(resume(&mut state, 1_000.0).is_none());
+    state
+}
+
+/// Lay eggs until there are enough for a nest and build it.
+fn build_nest(state: &mut State) {
+    let eggs = state.balance.eggs_per_nest;
+    for _ in 0..eggs {
+        assert!(actions(state).contains(&(Action::LayEgg, true)));
+        assert!(apply(state, Action::LayEgg));
+    }
+    assert_eq!(state.stock.get(Eggs), eggs);
+    let i = state
+        .recipes
+        .iter()
+        .position(|recipe| recipe.name == "build_nest")
+        .unwrap();
+    assert!(actions(state).contains(&(Action::Recipe(i), true)));
+    assert!(apply(state, Action::Recipe(i)));
+}
+
+#[test]
+fn starts_with_a_chicken() {
+    let state = new_farm();
+    assert_eq!(state.stock.get(Chickens), 1);
+    assert_eq!(state.stock.get(Eggs), 0);
+    assert_eq!(prestige_reward(&state), 0);
+    assert_eq!(actions(&state), vec![(Action::LayEgg, true)]);
+}
+
+#[test]
+fn lay_eggs_and_build_a_nest() {
+    let mut state = new_farm();
+    build_nest(&mut state);
+    assert_eq!(state.stock.get(Nests), 1);
+    assert_eq!(state.stock.get(Eggs), 0);
+    // The only chicken sits on the nest now.
+    assert!(actions(&state).contains(&(Action::LayEgg, false)));
+    assert!(!apply(&mut state, Action::LayEgg));
+    assert!(!apply(&mut state, Action::Recipe(usize::MAX)));
+    assert!(!apply(&mut state, Action::Prestige));
+}
+
+#[test]
+fn nests_hatch_and_chicks_grow_up() {
+    let mut state = new_farm();
+    build_nest(&mut state);
+    let balance = state.balance;
+    let mut steps = 0;
+    while steps <= balance.breeding_per_batch + balance.growing_up_ticks {
+        steps += tick(&mut state, 1.0);
+    }
+    assert_eq!(state.stock.get(Nests), 0);
+    assert_eq!(state.stock.get(Chicks), 0);
+    let grown = state.stock.get(Chickens) + state.stock.get(Roosters) - 1;
+    assert_eq!(grown + state.stock.get(Runaway), balance.chicks_per_nest);
+    assert!(state.unlocked.contains("first_chick"));
+    assert_eq!(event(&state), None);
+    assert!(!apply(&mut state, Action::Respond(true)));
+}
+
+#[test]
+fn ticks_are_fixed_steps() {
+    let mut state = new_farm();
+    assert_eq!(tick(&mut state, 0.0), 0);
+    assert_eq!(tick(&mut state, 0.5), 30);
+    // A frame that took far too long doesn't simulate all of it.
+    assert!(tick(&mut state, 3_600.0) < 3_600 * 60);
+}
+
+#[test]
+fn research_needs_prerequisites() {
+    let mut state = new_farm();
+    let upgrade = |state: &State, name| {
+        let i = state
+            .upgrades
+            .iter()
+            .position(|upgrade| upgrade.name == name);
+        Action::Research(i.unwrap())
+    };
+    state.stock.set(Eggs, 10_000);
+    state.stock.set(Corn, 10_000);
+    let warm_nests = upgrade(&state, "warm_nests");
+    assert!(!apply(&mut state, warm_nests));
+    assert!(!state.researched.contains("warm_nests"));
+    assert_eq!(state.stock.get(Eggs), 10_000);
+
+    let better_nests = upgrade(&state, "better_nests");
+    assert!(apply(&mut state, better_nests));
+    assert!(apply(&mut state, warm_nests));
+    assert!(state.researched.contains("warm_nests"));
+    assert!(!apply(&mut state, warm_nests));
+}
+
+#[test]
+fn actions_follow_their_enable_conditions() {
+    let mut state = new_farm();
+    let cap = state.balance.egg_cap;
+    state.stock.set(Chickens, 2);
+    state.stock.set(Eggs, cap);
+    assert!(!actions(&state)
+        .iter()
+        .any(|&(action, _)| action == Action::LayEgg));
+    assert!(!apply(&mut state, Action::LayEgg));
+    assert_eq!(state.stock.get(Eggs), cap);
+
+    // Too many nest builders, building nests by hand is retired.
+    let build_nest = state
+        .recipes
+        .iter()
+        .position(|recipe| recipe.name == "build_nest")
+        .unwrap();
+    state.stock.set(NestBuilders, 100);
+    assert!(!actions(&state).contains(&(Action::Recipe(build_nest), true)));
+    assert!(!apply(&mut state, Action::Recipe(build_nest)));
+    assert_eq!(state.stock.get(Nests), 0);
+}
+
+#[test]
+fn failed_spending_takes_nothing() {
+    let mut state = new_farm();
+    state.stock.set(Eggs, 10);
+    let eggs = state.stock.reader(Eggs);
+    let cost = Cost(vec![(Eggs, 5), (Corn, 1)]);
+    assert!(!state.stock.try_spend(&cost));
+    assert_eq!(state.stock.get(Eggs), 10);
+    assert!(!eggs.changed());
+    state.stock.set(Corn, 1);
+    assert!(state.stock.try_spend(&cost));
+    assert_eq!(state.stock.get(Eggs), 5);
+    assert_eq!(state.stock.get(Corn), 0);
+    assert!(eggs.changed());
+}
+
+#[test]
+fn stock_saturates() {
+    let mut state = new_farm();
+    state.stock.set(Corn, u64::MAX - 1);
+    state.stock.add(Corn, 10);
+    assert_eq!(state.stock.get(Corn), u64::MAX);
+}
+
+#[test]
+fn resume_catches_up_after_a_minute() {
+    for (away, caught_up) in [(59.0, false), (60.0, true), (3_600.0, true)] {
+        let mut state = State::new(Balance::default());
+        state.last_played.set(1_000.0);
+        let summary = resume(&mut state, 1_000.0 + away);
+        assert_eq!(summary.is_some(), caught_up, "away for {}s", away);
+        if let Some(summary) = summary {
+            assert_eq!(summary.seconds, away);
+        }
+    }
+}
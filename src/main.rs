@@ -1,13 +1,18 @@
-use std::sync::{Arc, Mutex};
-
-use macroquad::prelude::{
-    coroutines::{start_coroutine, Coroutine},
-    *,
+use std::collections::HashMap;
+
+use macroquad::prelude::*;
+use map_view::MapView;
+use tofuwabohu_core::{
+    actions, apply,
+    balance::Balance,
+    datastructures::{Computed, History, Reader, Sensor},
+    event, map_unlocked, prestige_reward,
+    recipes::Category,
+    resources::Resource,
+    save, Action, State, TICKS_PER_SECOND,
 };
-use save::Saveable;
 
-mod datastructures;
-mod save;
+mod map_view;
 
 fn window_conf() -> Conf {
     Conf {
@@ -17,125 +22,31 @@ fn window_conf() -> Conf {
     }
 }
 
-struct State {
-    chickens: Saveable<u64>,
-    roosters: Saveable<u64>,
-    nest_builders: Saveable<u64>,
-    chicks: Saveable<u64>,
-    runaway: Saveable<u64>,
-    nests: Saveable<u64>,
-    eggs: Saveable<u64>,
-    breeding: Saveable<u64>,
-    corn: Saveable<u64>,
-    corn_fetchers: Saveable<u64>,
-}
+/// Seconds over which production rates are averaged.
+const RATE_WINDOW: f64 = 5.0;
 
-struct Game {
-    state: Arc<Mutex<State>>,
-    nest_building: Option<Coroutine>,
-    corn_fetching: Option<Coroutine>,
+/// Recent values of the displayed resources, for showing their production rates.
+#[derive(Default)]
+struct Rates {
+    now: f64,
+    histories: HashMap<&'static str, History<u64>>,
 }
 
-impl Game {
-    fn chicks_growing_up(&self, n: u64) {
-        let state = self.state.clone();
-        start_coroutine(async move {
-            let mut chicks = n * 10;
-            for i in 0..100 {
-                let runaway = 100 * n / 7;
-                let remove = if runaway > 100 {
-                    runaway / 100
-                } else if i % runaway == 0 {
-                    1
-                } else {
-                    0
-                };
-
-                if chicks >= remove {
-                    let mut state = state.lock().unwrap();
-                    state.runaway += remove;
-                    state.chicks -= remove;
-                    chicks -= remove;
-                }
-                next_frame().await;
-            }
-            let mut state = state.lock().unwrap();
-            state.chicks -= chicks;
-            let half = chicks / 2;
-            let rem = chicks % 2;
-            state.chickens += half;
-            state.roosters += half + rem;
-        });
+impl Rates {
+    fn rate(&mut self, name: &'static str, val: u64) -> Option<f64> {
+        let history = self
+            .histories
+            .entry(name)
+            .or_insert_with(|| History::new(RATE_WINDOW));
+        history.record(self.now, val);
+        history.rate(RATE_WINDOW)
     }
-    fn cleanup(&mut self) {
-        let nest_building;
-        {
-            let mut state = self.state.lock().unwrap();
-            let state = &mut state;
-            let c = *state.chicks % 10;
-            state.chicks -= c;
-            state.runaway += c;
-            if state.chicks >= 10 {
-                self.chicks_growing_up(*state.chicks / 10);
-            }
-            nest_building = state.nest_builders > 0;
-        }
-        if nest_building {
-            self.nest_building();
-        }
-    }
-    fn nest_building(&mut self) {
-        if self.nest_building.is_none() {
-            let state = self.state.clone();
-            self.nest_building = Some(start_coroutine(async move {
-                loop {
-                    // wait around 10s per rooster
-                    let mut ticks = 600_u64;
-                    while ticks > 0 {
-                        ticks = ticks.saturating_sub(*state.lock().unwrap().nest_builders);
-                        next_frame().await;
-                    }
-                    {
-                        let mut state = state.lock().unwrap();
-                        let state = &mut state;
-                        let nb = state.nest_builders.checked_sub(600).unwrap_or(1);
-                        let corn = *state.corn;
-                        state.nests += nb;
-                        if corn > nb {
-                            state.corn -= nb;
-                        } else {
-                            state.roosters += nb - corn;
-                            state.nest_builders -= nb - corn;
-                            state.corn.set(0_u64);
-                        }
-                    }
-                    next_frame().await;
-                }
-            }))
-        }
-    }
-    fn rooster_fetch_corn(&mut self) {
-        if self.corn_fetching.is_none() {
-            let state = self.state.clone();
-            self.corn_fetching = Some(start_coroutine(async move {
-                loop {
-                    // wait around 10s per 1000 roosters
-                    let mut ticks = 600_u64;
-                    while ticks > 0 {
-                        ticks = ticks.saturating_sub(*state.lock().unwrap().corn_fetchers / 1000);
-                        next_frame().await;
-                    }
-                    {
-                        let mut state = state.lock().unwrap();
-                        let state = &mut state;
-                        let fetched = state.corn_fetchers.checked_sub(600).unwrap_or(1);
-                        state.corn += fetched * 500; // 400-500 corn per Kolben (https://faq-ans.com/de/Q%26A/page=5931035eafd04c6206fc17510a3af9b8#s0)
-                        state.roosters += fetched;
-                        state.corn_fetchers -= fetched;
-                    }
-                    next_frame().await;
-                }
-            }))
+
+    /// Record `val` and describe how fast it is changing, if it is.
+    fn suffix(&mut self, name: &'static str, val: u64) -> String {
+        match self.rate(name, val) {
+            Some(rate) if rate.abs() >= 0.1 => format!(" ({:+.1}/s)", rate),
+            _ => String::new(),
         }
     }
 }
@@ -154,13 +65,13 @@ impl Buttons {
     fn add(
         &mut self,
         label: impl Into<String>,
-        action: impl FnOnce(&mut State) + 'static,
+        action: Action,
         action_condition: bool,
         color: Color,
     ) {
         self.buttons.push(Button {
             label: label.into(),
-            action: action_condition.then(|| Box::new(action) as _),
+            action: action_condition.then(|| action),
             color,
         });
     }
@@ -168,111 +79,294 @@ impl Buttons {
 
 struct Button {
     label: String,
-    action: Option<Box<dyn FnOnce(&mut State)>>,
+    action: Option<Action>,
     color: Color,
 }
 
+/// What fills the screen.
+#[derive(Copy, Clone, PartialEq)]
+enum Screen {
+    Farm,
+    Achievements,
+}
+
+/// Seconds a newly reached achievement is announced for.
+const TOAST_SECONDS: f64 = 4.0;
+
+/// A unix time as UTC date and time, e.g. `2024-03-01 13:37`.
+fn format_time(unix: f64) -> String {
+    let secs = unix as i64;
+    let (days, secs) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+    // Days since 1970-01-01 to the civil calendar, from Howard Hinnant's date
+    // algorithms.
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60
+    )
+}
+
+/// Every achievement, with when it was reached.
+fn draw_achievements(state: &State, xb: f32, yb: f32) {
+    let size = yb * 0.5;
+    for (i, achievement) in state.achievements.iter().enumerate() {
+        let y = yb * 1.5 + i as f32 * size * 1.2;
+        match state.unlocked.time(achievement.name) {
+            Some(time) => {
+                let text = format!("{}: {}", achievement.label, format_time(time));
+                draw_text(&text, xb, y, size, GOLD);
+            }
+            None => draw_text(achievement.label, xb, y, size, DARKGRAY),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+const USAGE: &str = "\
+Usage: tofuwabohu [OPTIONS]
+
+Options:
+    --save-dir <DIR>    store saves in DIR (env: TOFUWABOHU_SAVE_DIR)
+    --profile <NAME>    use a separate save profile (env: TOFUWABOHU_PROFILE)
+    --reset             start from a fresh save
+    --read-only         never write anything to disk
+    --help              print this message";
+
+#[cfg(not(target_arch = "wasm32"))]
+fn exit_with_usage(msg: &str) -> ! {
+    eprintln!("error: {}\n\n{}", msg, USAGE);
+    std::process::exit(2)
+}
+
+/// Where and whether to save, from the command line and environment variables.
+#[cfg(not(target_arch = "wasm32"))]
+fn save_options() -> save::Options {
+    let mut options = save::Options::default();
+    let mut root = std::env::var_os("TOFUWABOHU_SAVE_DIR").map(std::path::PathBuf::from);
+    let mut profile = std::env::var("TOFUWABOHU_PROFILE").ok();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) => (flag.to_owned(), Some(value.to_owned())),
+            None => (arg, None),
+        };
+        let mut value = || {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .unwrap_or_else(|| exit_with_usage(&format!("missing value for `{}`", flag)))
+        };
+        match &*flag {
+            "--save-dir" => root = Some(value().into()),
+            "--profile" => profile = Some(value()),
+            "--reset" | "--read-only" if inline.is_some() => {
+                exit_with_usage(&format!("`{}` doesn't take a value", flag))
+            }
+            "--reset" => options.reset = true,
+            "--read-only" => options.read_only = true,
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            _ => exit_with_usage(&format!("unknown argument `{}`", flag)),
+        }
+    }
+
+    options.root = root.or_else(save::data_dir).unwrap_or_else(|| {
+        eprintln!("could not determine a data directory, saving to the current directory");
+        std::path::PathBuf::new()
+    });
+    if let Some(profile) = profile.filter(|p| !p.is_empty()) {
+        if profile.contains(|c| matches!(c, '/' | '\\')) || profile.starts_with('.') {
+            exit_with_usage(&format!("invalid profile name `{}`", profile));
+        }
+        options.root.push("profiles");
+        options.root.push(profile);
+    }
+    options
+}
+
+#[cfg(target_arch = "wasm32")]
+fn save_options() -> save::Options {
+    save::Options::default()
+}
+
 #[macroquad::main(window_conf)]
 async fn main() {
-    let state = Arc::new(Mutex::new(State {
-        chickens: Saveable::new(1_u64, "chickens"),
-        chicks: Saveable::new(0_u64, "chicks"),
-        runaway: Saveable::new(0_u64, "runaway"),
-        roosters: Saveable::new(0_u64, "roosters"),
-        nest_builders: Saveable::new(0_u64, "nest_builders"),
-        nests: Saveable::new(0_u64, "nests"),
-        breeding: Saveable::new(0_u64, "breeding"),
-        eggs: Saveable::new(0_u64, "eggs"),
-        corn: Saveable::new(0_u64, "corn"),
-        corn_fetchers: Saveable::new(0_u64, "corn_fetchers"),
-    }));
-    let mut game = Game {
-        state: state.clone(),
-        nest_building: None,
-        corn_fetching: None,
-    };
+    save::init(save_options());
+    let balance = Balance::load().unwrap_or_else(|err| {
+        eprintln!("balance.toml: {}, using the default balance", err);
+        Balance::default()
+    });
+    let mut state = State::load(balance);
+    // Achievements reached while away get announced too.
+    let mut announced = state.unlocked.0.len();
+    let mut away = None;
     save::transaction_step(|| {
-        game.cleanup();
+        away = tofuwabohu_core::resume(&mut state, macroquad::miniquad::date::now());
         async {}
     })
     .await;
-
-    let mut fps = [60; 60];
+    // Show the offline summary for a while.
+    let away_until = get_time() + 10.0;
+    let away = away.map(|summary| summary.lines()).unwrap_or_default();
+
+    let mut fps = History::new(1.0);
+    let mut rates = Rates::default();
+    let mut map_view = MapView::new();
+    // Total breeding progress, so its speed can be tracked across hatching.
+    let mut bred = 0_u64;
+    // Nests bred per second in tenths, and the breeding needed per nest.
+    let (pace, pace_reader) = Sensor::new((0_u64, state.balance.breeding_per_batch));
+    let breeding_text = Computed::new(
+        (
+            state.stock.reader(Resource::Nests),
+            state.stock.reader(Resource::Breeding),
+            pace_reader,
+        ),
+        |(nests, breeding, (tenths, batch))| {
+            if nests == 0 {
+                None
+            } else if tenths >= 20 {
+                // start displaying per second speed at 2/s
+                Some(format!("Breeding: {}.{} nests/s", tenths / 10, tenths % 10))
+            } else {
+                Some(format!(
+                    "Breeding: {:>2}% completed",
+                    breeding * 100 / batch
+                ))
+            }
+        },
+    );
+    // Only formatted again when the amount changes.
+    let resource_texts: Vec<(Resource, Computed<Reader<u64>, Option<String>>)> = Resource::ALL
+        .iter()
+        .map(|&resource| {
+            let text = Computed::new(state.stock.reader(resource), move |amount| {
+                resource
+                    .listed(amount)
+                    .then(|| format!("{} {}", amount, resource.name()))
+            });
+            (resource, text)
+        })
+        .collect();
+    let mut screen = Screen::Farm;
+    let mut toasts: Vec<(String, f64)> = vec![];
 
     save::transaction_loop(|| {
         let xb = screen_width() * 0.1;
         let yb = screen_height() * 0.1;
 
         // Logic
-        let mut state = state.lock().unwrap();
-        let mut state = &mut *state;
-
-        if state.breeding > 1000 {
-            let n = *state.breeding / 1000;
-            state.breeding -= 1000 * n;
-            state.chicks += n * 10;
-            game.chicks_growing_up(n);
-            state.nests -= n;
-        }
-        state.breeding += *state.nests;
+        let nests = state.stock.get(Resource::Nests);
+        bred += nests * tofuwabohu_core::tick(&mut state, get_frame_time() as f64);
+        state.last_played.set(macroquad::miniquad::date::now());
 
         // Drawing
         clear_background(BLACK);
         let thickness = (xb + yb) / 10.0;
 
-        let mut messages = Messages::default();
+        for (name, _) in state.unlocked.0.iter().skip(announced) {
+            if let Some(achievement) = state.achievements.iter().find(|a| a.name == name) {
+                let text = format!("Achievement: {}", achievement.label);
+                toasts.push((text, get_time() + TOAST_SECONDS));
+            }
+        }
+        announced = state.unlocked.0.len();
+        toasts.retain(|&(_, until)| get_time() < until);
+        for (i, (text, _)) in toasts.iter().enumerate() {
+            let size = yb * 0.4;
+            let width = measure_text(text, None, size as _, 1.0).width;
+            let y = yb * 1.2 + i as f32 * size * 1.2;
+            draw_text(text, screen_width() - xb - width, y, size, GOLD);
+        }
 
-        if is_key_down(KeyCode::Space) {
-            fps.rotate_right(1);
-            fps[0] = get_fps();
-            messages
-                .msgs
-                .push(format!("{} fps", fps.iter().sum::<i32>() / 60));
+        let toggle = Rect::new(screen_width() - xb * 3.0, yb * 0.1, xb * 2.0, yb * 0.6);
+        let label = match screen {
+            Screen::Farm => "Achievements",
+            Screen::Achievements => "Back",
+        };
+        draw_rectangle_lines(toggle.x, toggle.y, toggle.w, toggle.h, 2.0, GOLD);
+        draw_text(
+            label,
+            toggle.x + 5.0,
+            toggle.y + toggle.h * 0.7,
+            toggle.h * 0.6,
+            GOLD,
+        );
+        if is_mouse_button_pressed(MouseButton::Left) {
+            let (x, y) = mouse_position();
+            if toggle.contains(vec2(x, y)) {
+                screen = match screen {
+                    Screen::Farm => Screen::Achievements,
+                    Screen::Achievements => Screen::Farm,
+                };
+            }
+        }
+        if screen == Screen::Achievements {
+            draw_achievements(&state, xb, yb);
+            return next_frame();
         }
 
-        messages.msgs.push(format!("{} chickens", *state.chickens));
+        let mut messages = Messages::default();
 
-        if state.runaway > 0 {
-            messages.msgs.push(format!("{} ran away", *state.runaway));
+        if get_time() < away_until {
+            messages.msgs.extend(away.iter().cloned());
         }
 
-        if state.roosters > 0 {
+        rates.now = get_time();
+        fps.record(rates.now, get_fps());
+        if is_key_down(KeyCode::Space) {
             messages
                 .msgs
-                .push(format!("{} useless roosters", *state.roosters));
+                .push(format!("{:.0} fps", fps.average(1.0).unwrap_or_default()));
         }
 
-        if state.chicks > 0 {
-            messages.msgs.push(format!("{} chicks", *state.chicks));
+        for (resource, text) in &resource_texts {
+            if let Some(Some(text)) = text.get() {
+                let amount = state.stock.get(*resource);
+                let suffix = rates.suffix(resource.key(), amount);
+                messages.msgs.push(text + &suffix);
+            }
         }
 
-        if state.eggs > 0 {
-            messages.msgs.push(format!("{} eggs", *state.eggs));
+        if let Some(event) = event(&state) {
+            messages.msgs.push(format!(
+                "{} ({}s left)",
+                event.describe(),
+                event.remaining / TICKS_PER_SECOND
+            ));
         }
 
-        if state.corn > 0 {
-            messages.msgs.push(format!("{} corn", *state.corn));
+        if *state.feathers > 0 {
+            messages.msgs.push(format!(
+                "{} golden feathers (+{}%)",
+                *state.feathers,
+                *state.feathers * state.balance.feather_percent
+            ));
         }
 
-        if state.nest_builders > 0 {
-            messages
-                .msgs
-                .push(format!("{} nest_builders", *state.nest_builders));
+        let batch = state.balance.breeding_per_batch;
+        let breeding = rates.rate("bred", bred).unwrap_or_default() / batch as f64;
+        let tenths = (breeding * 10.0).round() as u64;
+        if pace.with(|&old| old != (tenths, batch)) {
+            pace.set((tenths, batch));
         }
-
-        if state.nests > 0 {
-            messages.msgs.push(format!("{} nests", *state.nests));
-            // start displaying per second speed at 2/s
-            if *state.nests * 30 > 1000 {
-                messages
-                    .msgs
-                    .push(format!("Breeding: {} nests/s", *state.nests * 60 / 1000));
-            } else {
-                messages.msgs.push(format!(
-                    "Breeding: {:>2}% completed",
-                    *state.breeding * 100 / 1000
-                ));
-            }
+        if let Some(Some(text)) = breeding_text.get() {
+            messages.msgs.push(text);
         }
 
         let dims = measure_text(&messages.msgs[0], None, yb as _, 1.0);
@@ -285,66 +379,40 @@ async fn main() {
 
         let mut buttons = Buttons::default();
 
-        if state.nest_builders < 100 && (state.eggs >= 10 || state.chickens > 1) {
-            buttons.add(
-                "Build Nest",
-                |state| {
-                    state.eggs -= 10;
-                    state.nests += 1;
+        for (action, enabled) in actions(&state) {
+            let (label, color) = match action {
+                Action::Recipe(i) => {
+                    let recipe = &state.recipes[i];
+                    let color = match recipe.category {
+                        Category::Build => RED,
+                        Category::Employ => YELLOW,
+                    };
+                    (recipe.label.clone(), color)
+                }
+                Action::LayEgg => ("Lay Egg".to_owned(), GREEN),
+                Action::Research(i) => (state.upgrades[i].label.to_owned(), SKYBLUE),
+                Action::Prestige => (
+                    format!("Start over for {} golden feathers", prestige_reward(&state)),
+                    MAGENTA,
+                ),
+                Action::Respond(true) => match event(&state) {
+                    Some(event) => (event.response(&state.balance), ORANGE),
+                    None => continue,
                 },
-                state.nests < *state.chickens && state.eggs >= 10,
-                RED,
-            );
-        }
-
-        if state.nest_builders > 1 || state.nests > 100 || state.chickens > 5000 {
-            let n = match *state.nest_builders {
-                0..=99 => (1, "rooster"),
-                100..=1000 => (10, "roosters"),
-                _ => (1000, "roosters"),
+                Action::Respond(false) => ("Let it be".to_owned(), ORANGE),
             };
-            buttons.add(
-                "Employ rooster for nest building",
-                |state| {
-                    state.roosters -= 1;
-                    state.nest_builders += 1;
-                },
-                state.roosters > 0,
-                YELLOW,
-            );
-            game.nest_building();
+            buttons.add(label, action, enabled, color);
         }
 
-        if state.corn > 1
-            || state.nest_builders > 100
-            || state.corn_fetchers > 0
-            || state.chickens > 10000
-        {
-            buttons.add(
-                "Send 1k roosters to fetch corn",
-                |state| {
-                    state.roosters -= 1000;
-                    state.corn_fetchers += 1000;
-                },
-                state.roosters >= 1000,
-                YELLOW,
-            );
-            game.rooster_fetch_corn();
-        }
+        let button_height = yb * 1.5;
+        let button_width = screen_width() - xb * 2.0;
 
-        if state.eggs < 1000 {
-            buttons.add(
-                "Lay Egg",
-                |state| {
-                    state.eggs += *state.chickens - *state.nests;
-                },
-                state.chickens > *state.nests,
-                GREEN,
-            );
+        let top = screen_height() * 0.3;
+        let bottom = screen_height() - button_height * buttons.buttons.len() as f32 - yb * 0.2;
+        if map_unlocked(&state) && bottom > top {
+            map_view.draw(&mut state, Rect::new(xb, top, button_width, bottom - top));
         }
 
-        let button_height = yb * 1.5;
-        let button_width = screen_width() - xb * 2.0;
         for (i, button) in buttons.buttons.into_iter().rev().enumerate() {
             draw_rectangle_lines(
                 xb,
@@ -378,7 +446,7 @@ async fn main() {
                             ..(screen_height() - button_height * i as f32))
                             .contains(&y)
                         {
-                            action(&mut state)
+                            apply(&mut state, action);
                         }
                     }
                 }
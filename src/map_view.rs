@@ -0,0 +1,314 @@
+use std::{fmt, str::FromStr};
+
+use hex2d::{Coordinate, Spacing};
+use macroquad::prelude::*;
+use tofuwabohu_core::{
+    map::Building,
+    place,
+    save::Saveable,
+    scout, scouting_cost,
+    terrain::{Terrain, Visibility},
+    State,
+};
+
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 8.0;
+/// Zoom factor of a single scroll wheel step.
+const WHEEL_ZOOM: f32 = 1.1;
+/// Pixels the pointer has to move before a press becomes a drag instead of a click.
+const DRAG_THRESHOLD: f32 = 5.0;
+
+/// Tiles from the center to the edge of the map at zoom 1.
+const VIEW_RADIUS: i32 = 5;
+
+/// Hexagons of size 1, everything else is scaled from that.
+const UNIT: Spacing = Spacing::PointyTop(1.0);
+
+fn color(building: Building) -> Color {
+    match building {
+        Building::Nest => BROWN,
+        Building::Coop => RED,
+        Building::CornField => GOLD,
+    }
+}
+
+fn terrain_color(terrain: Terrain) -> Color {
+    match terrain {
+        Terrain::Meadow => GREEN,
+        Terrain::Forest => DARKGREEN,
+        Terrain::Water => BLUE,
+        Terrain::CornField => YELLOW,
+        Terrain::Rocky => GRAY,
+    }
+}
+
+/// Where the map is looked at from, in tile sizes from the center, and how closely.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Camera {
+    x: f32,
+    y: f32,
+    zoom: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            zoom: 1.0,
+        }
+    }
+}
+
+impl fmt::Display for Camera {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.x, self.y, self.zoom)
+    }
+}
+
+impl FromStr for Camera {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(' ').map(str::parse::<f32>);
+        let mut next = || match parts.next() {
+            Some(Ok(val)) => Ok(val),
+            _ => Err(format!("invalid camera `{}`", s)),
+        };
+        Ok(Self {
+            x: next()?,
+            y: next()?,
+            zoom: next()?,
+        })
+    }
+}
+
+/// The farm map, with a row of buildings to pick from above it.
+pub struct MapView {
+    selected: Building,
+    /// Only saved once a gesture is over, `view` is what's shown meanwhile.
+    camera: Saveable<Camera>,
+    view: Camera,
+    /// Where the mouse button went down, while it is held.
+    press: Option<Vec2>,
+    /// Whether the held mouse button has been moved far enough to pan.
+    dragged: bool,
+    last: Vec2,
+    /// Distance between and midpoint of the two fingers of a pinch.
+    pinch: Option<(f32, Vec2)>,
+}
+
+impl MapView {
+    pub fn new() -> Self {
+        let camera = Saveable::default("camera");
+        Self {
+            selected: Building::Nest,
+            view: *camera,
+            camera,
+            press: None,
+            dragged: false,
+            last: vec2(0.0, 0.0),
+            pinch: None,
+        }
+    }
+
+    /// Draw into `area` and build the selected building on the tile that was clicked, or
+    /// scout it if it has only been glimpsed.
+    pub fn draw(&mut self, state: &mut State, area: Rect) {
+        let palette_height = area.h * 0.15;
+        self.draw_palette(state, Rect::new(area.x, area.y, area.w, palette_height));
+        let area = Rect::new(
+            area.x,
+            area.y + palette_height,
+            area.w,
+            area.h - palette_height,
+        );
+        // The tile size at which `VIEW_RADIUS` fits at zoom 1.
+        let tiles = (VIEW_RADIUS * 2 + 1) as f32;
+        let scale = (area.w / tiles / 3f32.sqrt()).min(area.h / tiles / 1.5);
+
+        let limit = state.land.radius() as f32 * 2.0;
+        if let Some(click) = self.input(area, scale, limit) {
+            let at = self.pick(click, area, scale);
+            match state.land.visibility(at) {
+                Some(Visibility::Glimpsed) => {
+                    scout(state, at);
+                }
+                Some(Visibility::Revealed) => {
+                    place(state, at, self.selected);
+                }
+                None => {}
+            }
+        }
+
+        let size = scale * self.view.zoom;
+        // Only draw the tiles that are completely inside the area.
+        let inner = Rect::new(
+            area.x + size,
+            area.y + size,
+            area.w - size * 2.0,
+            area.h - size * 2.0,
+        );
+        for (at, terrain) in state.land.iter() {
+            let pos = self.to_screen(at, area, scale);
+            if inner.contains(pos) {
+                let mut fill = terrain_color(terrain);
+                if state.land.visibility(at) == Some(Visibility::Glimpsed) {
+                    // Only vaguely seen through the fog.
+                    fill = Color::new(fill.r * 0.4, fill.g * 0.4, fill.b * 0.4, 1.0);
+                }
+                draw_hexagon(pos.x, pos.y, size, 1.0, true, BLACK, fill);
+                if let Some(building) = state.map.get(at) {
+                    draw_hexagon(pos.x, pos.y, size * 0.6, 0.0, true, BLACK, color(building));
+                }
+            }
+        }
+
+        // The way the corn fetchers take.
+        if let Some(route) = &state.route {
+            for step in route.tiles.windows(2) {
+                let from = self.to_screen(step[0], area, scale);
+                let to = self.to_screen(step[1], area, scale);
+                if inner.contains(from) && inner.contains(to) {
+                    draw_line(from.x, from.y, to.x, to.y, size * 0.15, ORANGE);
+                }
+            }
+        }
+
+        let (x, y) = mouse_position();
+        let mouse = vec2(x, y);
+        let hovered = self.pick(mouse, area, scale);
+        let pos = self.to_screen(hovered, area, scale);
+        if !area.contains(mouse) || !inner.contains(pos) {
+            return;
+        }
+        let clickable = match state.land.visibility(hovered) {
+            Some(Visibility::Glimpsed) => scouting_cost(state).affordable(&state.stock),
+            Some(Visibility::Revealed) => {
+                state.land.buildable(hovered)
+                    && state.map.get(hovered).is_none()
+                    && self.selected.cost(&state.balance).affordable(&state.stock)
+            }
+            None => return,
+        };
+        let border = if clickable { WHITE } else { DARKGRAY };
+        draw_hexagon(pos.x, pos.y, size, 3.0, true, border, BLANK);
+    }
+
+    /// Pan and zoom according to mouse, wheel and touch input. Returns where the map was
+    /// clicked, if it was.
+    fn input(&mut self, area: Rect, scale: f32, limit: f32) -> Option<Vec2> {
+        let click = self.gesture(area, scale);
+        let view = &mut self.view;
+        view.x = view.x.clamp(-limit, limit);
+        view.y = view.y.clamp(-limit, limit);
+        if self.press.is_none() && self.pinch.is_none() && *self.camera != self.view {
+            self.camera.set(self.view);
+        }
+        click
+    }
+
+    fn gesture(&mut self, area: Rect, scale: f32) -> Option<Vec2> {
+        let touches = touches();
+        if touches.len() >= 2 {
+            let (a, b) = (touches[0].position, touches[1].position);
+            let distance = (a - b).length();
+            let mid = (a + b) / 2.0;
+            if let Some((prev_distance, prev_mid)) = self.pinch {
+                if prev_distance > 0.0 {
+                    self.zoom_at(mid, distance / prev_distance, area, scale);
+                }
+                self.pan(mid - prev_mid, scale);
+            }
+            self.pinch = Some((distance, mid));
+            // A pinch is never a click.
+            self.press = None;
+            return None;
+        }
+        self.pinch = None;
+
+        let (x, y) = mouse_position();
+        let mouse = vec2(x, y);
+        let (_, wheel) = mouse_wheel();
+        if wheel != 0.0 && area.contains(mouse) {
+            let factor = if wheel > 0.0 {
+                WHEEL_ZOOM
+            } else {
+                1.0 / WHEEL_ZOOM
+            };
+            self.zoom_at(mouse, factor, area, scale);
+        }
+
+        if is_mouse_button_pressed(MouseButton::Left) && area.contains(mouse) {
+            self.press = Some(mouse);
+            self.dragged = false;
+            self.last = mouse;
+        }
+        let start = self.press?;
+        if (mouse - start).length() > DRAG_THRESHOLD {
+            self.dragged = true;
+        }
+        if self.dragged {
+            self.pan(mouse - self.last, scale);
+        }
+        self.last = mouse;
+        if is_mouse_button_down(MouseButton::Left) {
+            return None;
+        }
+        self.press = None;
+        (!self.dragged).then(|| mouse)
+    }
+
+    fn pan(&mut self, by: Vec2, scale: f32) {
+        self.view.x -= by.x / (scale * self.view.zoom);
+        self.view.y -= by.y / (scale * self.view.zoom);
+    }
+
+    /// Zoom by `factor`, keeping what's under `point` where it is.
+    fn zoom_at(&mut self, point: Vec2, factor: f32, area: Rect, scale: f32) {
+        let world = self.to_world(point, area, scale);
+        self.view.zoom = (self.view.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        let offset = (point - area.center()) / (scale * self.view.zoom);
+        self.view.x = world.x - offset.x;
+        self.view.y = world.y - offset.y;
+    }
+
+    fn to_world(&self, point: Vec2, area: Rect, scale: f32) -> Vec2 {
+        (point - area.center()) / (scale * self.view.zoom) + vec2(self.view.x, self.view.y)
+    }
+
+    fn to_screen(&self, at: Coordinate, area: Rect, scale: f32) -> Vec2 {
+        let (x, y) = at.to_pixel(UNIT);
+        area.center() + (vec2(x, y) - vec2(self.view.x, self.view.y)) * scale * self.view.zoom
+    }
+
+    /// The tile under `point`.
+    fn pick(&self, point: Vec2, area: Rect, scale: f32) -> Coordinate {
+        let world = self.to_world(point, area, scale);
+        Coordinate::from_pixel(world.x, world.y, UNIT)
+    }
+
+    fn draw_palette(&mut self, state: &State, area: Rect) {
+        let width = area.w / Building::ALL.len() as f32;
+        for (i, building) in Building::ALL.iter().copied().enumerate() {
+            let rect = Rect::new(area.x + width * i as f32, area.y, width, area.h);
+            let thickness = if building == self.selected { 4.0 } else { 1.0 };
+            draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, thickness, color(building));
+            let affordable = building.cost(&state.balance).affordable(&state.stock);
+            draw_text(
+                building.name(),
+                rect.x + rect.w * 0.1,
+                rect.y + rect.h * 0.7,
+                rect.h * 0.6,
+                if affordable { WHITE } else { DARKGRAY },
+            );
+
+            if is_mouse_button_pressed(MouseButton::Left) {
+                let (x, y) = mouse_position();
+                if rect.contains(vec2(x, y)) {
+                    self.selected = building;
+                }
+            }
+        }
+    }
+}